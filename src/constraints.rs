@@ -1,49 +1,126 @@
+use std::cmp::Ordering;
 use std::iter::repeat_n;
 
+use itertools::Itertools;
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+// Miller-Rabin, these bases being enough for every u64
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(p) = BASES.iter().find(|p| n.is_multiple_of(**p)) {
+        return n == *p;
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|a| {
+        let mut x = pow_mod(*a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        (1..s).any(|_| {
+            x = mul_mod(x, x, n);
+            x == n - 1
+        })
+    })
+}
+
+// Pollard's rho with Brent's cycle detection, n being odd and composite
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1.. {
+        let f = |x: u64| ((mul_mod(x, x, n) as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2u64, 2u64, 1u64);
+        let mut power = 1;
+        let mut length = 0;
+        while d == 1 {
+            if power == length {
+                x = y;
+                power *= 2;
+                length = 0;
+            }
+            y = f(y);
+            length += 1;
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
 fn prime_factors(mut n: u64) -> Vec<u64> {
     let mut factors = Vec::new();
-    let mut m = 2u64;
+    if n == 0 {
+        return factors;
+    }
 
-    while n >= m * m {
-        while n % m == 0 {
+    // Small factors are cheaper to find by trial division
+    for m in 2u64..100 {
+        while n.is_multiple_of(m) {
             n /= m;
             factors.push(m);
         }
-        m += 1;
     }
 
-    if n != 1 {
-        factors.push(n);
+    let mut pending = vec![n];
+    while let Some(n) = pending.pop() {
+        if n == 1 {
+            continue;
+        }
+        if is_prime(n) {
+            factors.push(n);
+        } else {
+            let d = pollard_rho(n);
+            pending.push(d);
+            pending.push(n / d);
+        }
     }
 
+    factors.sort();
     factors
 }
 
 fn factors(n: u64) -> Vec<u64> {
-    let prime_factors = prime_factors(n);
-    let mut factors = Vec::new();
-    let mut selected: Vec<bool> = repeat_n(false, prime_factors.len()).collect();
+    let mut factors = vec![1u64];
 
-    while selected.iter().any(|s| !s) {
-        factors.push(
-            prime_factors
-                .iter()
-                .zip(selected.iter())
-                .fold(1u64, |acc, (p, s)| if *s { acc * p } else { acc }),
-        );
-        for s in selected.iter_mut() {
-            if *s {
-                *s = false;
-            } else {
-                *s = true;
-                break;
+    for (count, p) in prime_factors(n).into_iter().dedup_with_count() {
+        let divisors = factors.len();
+        let mut power = 1u64;
+        for _ in 0..count {
+            power *= p;
+            for i in 0..divisors {
+                factors.push(factors[i] * power);
             }
         }
     }
 
-    factors.push(n);
     factors.sort();
-    factors.dedup();
     factors
 }
 
@@ -61,65 +138,170 @@ fn checked_product(values: impl AsRef<[u64]>) -> Option<u64> {
         .try_fold(1u64, |acc, v| acc.checked_mul(*v))
 }
 
-pub fn add_enumerator(target: u64, number_count: usize, number_max: u64) -> Vec<Vec<u64>> {
-    let mut solutions = Vec::new();
-    let mut current: Vec<u64> = std::iter::repeat_n(1, number_count).collect();
-
-    'search: loop {
-        if let Some(remainder) =
-            checked_sum(&current[0..number_count - 1]).and_then(|s| target.checked_sub(s))
-            && remainder <= number_max
-        {
-            current[number_count - 1] = remainder;
-            solutions.push(current.clone());
-        }
-        for i in (0..number_count - 1).rev() {
-            let n = current[i] + 1;
-            current[i..].fill(n);
-            if current[i] < number_max && checked_sum(&current).is_some_and(|s| s <= target) {
-                break;
+// Enumerates nondecreasing values, the last one being derived from the others when it fits,
+// while prefixes that can no longer be completed are skipped: fits tells whether a prefix,
+// followed by values between its last one and number_max, is too small or too large
+fn prefix_enumerator(
+    number_count: usize,
+    number_max: u64,
+    complete: impl Fn(&[u64]) -> Option<u64>,
+    fits: impl Fn(&[u64]) -> Ordering,
+) -> impl Iterator<Item = Vec<u64>> {
+    let mut current = Some(repeat_n(1, number_count).collect::<Vec<u64>>());
+    std::iter::from_fn(move || {
+        while let Some(values) = current.as_mut() {
+            let solution = complete(&values[0..number_count - 1]).map(|last| {
+                values[number_count - 1] = last;
+                values.clone()
+            });
+            let advanced = (0..number_count - 1).rev().any(|i| {
+                for n in values[i] + 1..=number_max {
+                    values[i..].fill(n);
+                    match fits(&values[..=i]) {
+                        Ordering::Less => continue,
+                        Ordering::Equal => return true,
+                        Ordering::Greater => return false,
+                    }
+                }
+                false
+            });
+            if !advanced {
+                current = None;
             }
-            if i == 0 {
-                break 'search;
+            if solution.is_some() {
+                return solution;
             }
         }
-    }
-
-    solutions
+        None
+    })
 }
 
-pub fn sub_enumerator(target: u64, number_count: usize, number_max: u64) -> Vec<Vec<u64>> {
-    let mut solutions = Vec::new();
-    let mut current: Vec<u64> = std::iter::repeat_n(1, number_count).collect();
-
-    'search: loop {
-        if let Some(remainder) =
-            checked_sum(&current[0..number_count - 1]).and_then(|s| s.checked_add(target))
-            && remainder <= number_max
-        {
-            current[number_count - 1] = remainder;
-            solutions.push(current.clone());
-        }
-        for i in (0..number_count - 1).rev() {
-            let n = current[i] + 1;
-            current[i..].fill(n);
-            if current[i] < number_max
-                && checked_sum(&current[0..number_count - 1])
-                    .and_then(|s| s.checked_add(target))
-                    .is_some_and(|s| s <= number_max)
-            {
-                break;
+pub fn add_enumerator(
+    target: u64,
+    number_count: usize,
+    number_max: u64,
+) -> impl Iterator<Item = Vec<u64>> {
+    prefix_enumerator(
+        number_count,
+        number_max,
+        move |prefix| {
+            checked_sum(prefix)
+                .and_then(|s| target.checked_sub(s))
+                .filter(|r| *r <= number_max && *r >= prefix.last().copied().unwrap_or(1))
+        },
+        move |prefix| {
+            let rest = (number_count - prefix.len()) as u64;
+            let last = prefix[prefix.len() - 1];
+            match checked_sum(prefix) {
+                Some(s) if s.saturating_add(rest.saturating_mul(number_max)) < target => {
+                    Ordering::Less
+                }
+                Some(s) if s.saturating_add(rest.saturating_mul(last)) <= target => Ordering::Equal,
+                _ => Ordering::Greater,
             }
-            if i == 0 {
-                break 'search;
+        },
+    )
+}
+
+// Number of solutions add_enumerator would produce, saturating instead of overflowing
+pub fn add_enumeration_size(target: u64, number_count: usize, number_max: u64) -> u64 {
+    let max_sum = (number_count as u64).checked_mul(number_max);
+    if target < number_count as u64 || max_sum.is_some_and(|m| target > m) {
+        return 0;
+    }
+    // Replacing each value v by number_max + 1 - v maps the multisets summing to the target onto
+    // those summing to its reflection, so the smaller of the two sizes the table
+    let target = number_max
+        .checked_add(1)
+        .and_then(|m| (number_count as u64).checked_mul(m))
+        .map_or(target, |s| target.min(s - target));
+    let Ok(target) = usize::try_from(target) else {
+        return u64::MAX;
+    };
+    // counts[k][s] is the number of multisets of k values summing to s, values being added in
+    // increasing order so that each multiset is only counted once
+    let mut counts = vec![vec![0u64; target + 1]; number_count + 1];
+    counts[0][0] = 1;
+    for value in 1..=number_max.min(target as u64) as usize {
+        for k in 1..=number_count {
+            for s in value..=target {
+                counts[k][s] = counts[k][s].saturating_add(counts[k - 1][s - value]);
             }
         }
     }
+    counts[number_count][target]
+}
+
+// Distinct values increasing by at least one at each position, so taking away its position from
+// each value leaves nondecreasing values summing to the target minus the positions
+fn unique_add_shift(target: u64, number_count: usize, number_max: u64) -> Option<(u64, u64)> {
+    let count = number_count as u64;
+    let shift = count.checked_mul(count.saturating_sub(1))? / 2;
+    let number_max = number_max
+        .checked_sub(count.saturating_sub(1))
+        .filter(|m| *m > 0)?;
+    Some((target.checked_sub(shift)?, number_max))
+}
+
+pub fn unique_add_enumerator(
+    target: u64,
+    number_count: usize,
+    number_max: u64,
+) -> impl Iterator<Item = Vec<u64>> {
+    unique_add_shift(target, number_count, number_max)
+        .into_iter()
+        .flat_map(move |(target, number_max)| add_enumerator(target, number_count, number_max))
+        .map(|values| {
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| v + i as u64)
+                .collect()
+        })
+}
 
-    solutions
+// Number of solutions unique_add_enumerator would produce, saturating instead of overflowing
+pub fn unique_add_enumeration_size(target: u64, number_count: usize, number_max: u64) -> u64 {
+    unique_add_shift(target, number_count, number_max).map_or(0, |(target, number_max)| {
+        add_enumeration_size(target, number_count, number_max)
+    })
 }
 
-pub fn mul_enumerator(target: u64, number_count: usize, number_max: u64) -> Vec<Vec<u64>> {
+pub fn sub_enumerator(
+    target: u64,
+    number_count: usize,
+    number_max: u64,
+) -> impl Iterator<Item = Vec<u64>> {
+    prefix_enumerator(
+        number_count,
+        number_max,
+        move |prefix| {
+            checked_sum(prefix)
+                .and_then(|s| s.checked_add(target))
+                .filter(|r| *r <= number_max)
+        },
+        move |prefix| {
+            // Only the values before the last one are summed
+            let rest = (number_count - 1 - prefix.len()) as u64;
+            let last = prefix[prefix.len() - 1];
+            let fits = checked_sum(prefix)
+                .and_then(|s| s.checked_add(rest.checked_mul(last)?))
+                .and_then(|s| s.checked_add(target))
+                .is_some_and(|s| s <= number_max);
+            if fits {
+                Ordering::Equal
+            } else {
+                Ordering::Greater
+            }
+        },
+    )
+}
+
+pub fn mul_enumerator(
+    target: u64,
+    number_count: usize,
+    number_max: u64,
+) -> impl Iterator<Item = Vec<u64>> {
     let factors = factors(target);
     let factor_upper_bound = factors
         .iter()
@@ -127,91 +309,93 @@ pub fn mul_enumerator(target: u64, number_count: usize, number_max: u64) -> Vec<
         .find(|(_, f)| **f > number_max)
         .map(|(i, _)| i)
         .unwrap_or(factors.len());
-    let mut solutions = Vec::new();
     let mut current = Vec::with_capacity(number_count - 1);
     let mut remainder = target;
     current.push(0);
 
-    while !current.is_empty() {
-        let depth = current.len();
-        let mut backtrack = true;
-        // Adjust last idx of current so that current forms a solution prefix
-        for (i, f) in factors
-            .iter()
-            .enumerate()
-            .take(factor_upper_bound)
-            .skip(current[depth - 1])
-        {
-            // Factor pow check is meant to prune impossible prefixes
-            if remainder % f == 0
-                && f.checked_pow((number_count + 1 - depth) as u32)
-                    .is_some_and(|p| p <= remainder)
+    std::iter::from_fn(move || {
+        while !current.is_empty() {
+            let depth = current.len();
+            let mut backtrack = true;
+            // Adjust last idx of current so that current forms a solution prefix
+            for (i, f) in factors
+                .iter()
+                .enumerate()
+                .take(factor_upper_bound)
+                .skip(current[depth - 1])
             {
-                remainder /= f;
-                current[depth - 1] = i;
-                backtrack = false;
-                break;
-            }
-        }
-        if backtrack {
-            // Backtrack if it failed to find a solution prefix
-            current.pop();
-            if let Some(prev_idx) = current.last_mut() {
-                remainder *= factors[*prev_idx];
-                *prev_idx += 1;
+                // Factor pow check is meant to prune impossible prefixes
+                if remainder.is_multiple_of(*f)
+                    && f.checked_pow((number_count + 1 - depth) as u32)
+                        .is_some_and(|p| p <= remainder)
+                {
+                    remainder /= f;
+                    current[depth - 1] = i;
+                    backtrack = false;
+                    break;
+                }
             }
-        } else if current.len() < number_count - 1 {
-            // If we have a valid but incomplete solution prefix, we extend by one number
-            current.push(current[depth - 1]);
-        } else if current.len() == number_count - 1 {
-            // If our prefix has reached number_count - 1, we check if it forms a valid solution with the remainder
-            if factors[current[depth - 1]] <= remainder && remainder <= number_max {
-                solutions.push(
-                    current
-                        .iter()
-                        .map(|i| factors[*i])
-                        .chain([remainder])
-                        .collect(),
-                );
+            if backtrack {
+                // Backtrack if it failed to find a solution prefix
+                current.pop();
+                if let Some(prev_idx) = current.last_mut() {
+                    remainder *= factors[*prev_idx];
+                    *prev_idx += 1;
+                }
+            } else if current.len() < number_count - 1 {
+                // If we have a valid but incomplete solution prefix, we extend by one number
+                current.push(current[depth - 1]);
+            } else if current.len() == number_count - 1 {
+                // If our prefix has reached number_count - 1, we check if it forms a valid solution with the remainder
+                let solution = (factors[current[depth - 1]] <= remainder
+                    && remainder <= number_max)
+                    .then(|| {
+                        current
+                            .iter()
+                            .map(|i| factors[*i])
+                            .chain([remainder])
+                            .collect()
+                    });
+                // we start over the search from the last index of current
+                remainder *= factors[current[depth - 1]];
+                current[depth - 1] += 1;
+                if solution.is_some() {
+                    return solution;
+                }
             }
-            // we start over the search from the last index of current
-            remainder *= factors[current[depth - 1]];
-            current[depth - 1] += 1;
         }
-    }
-
-    solutions
+        None
+    })
 }
 
-pub fn div_enumerator(target: u64, number_count: usize, number_max: u64) -> Vec<Vec<u64>> {
-    let mut solutions = Vec::new();
-    let mut current: Vec<u64> = std::iter::repeat_n(1, number_count).collect();
-
-    'search: loop {
-        if let Some(remainder) =
-            checked_product(&current[0..number_count - 1]).and_then(|p| p.checked_mul(target))
-            && remainder <= number_max
-        {
-            current[number_count - 1] = remainder;
-            solutions.push(current.clone());
-        }
-        for i in (0..number_count - 1).rev() {
-            let n = current[i] + 1;
-            current[i..].fill(n);
-            if current[i] < number_max
-                && checked_product(&current[0..number_count - 1])
-                    .and_then(|p| p.checked_mul(target))
-                    .is_some_and(|p| p <= number_max)
-            {
-                break;
-            }
-            if i == 0 {
-                break 'search;
+pub fn div_enumerator(
+    target: u64,
+    number_count: usize,
+    number_max: u64,
+) -> impl Iterator<Item = Vec<u64>> {
+    prefix_enumerator(
+        number_count,
+        number_max,
+        move |prefix| {
+            checked_product(prefix)
+                .and_then(|p| p.checked_mul(target))
+                .filter(|r| *r <= number_max)
+        },
+        move |prefix| {
+            // Only the values before the last one are multiplied
+            let rest = (number_count - 1 - prefix.len()) as u32;
+            let last = prefix[prefix.len() - 1];
+            let fits = checked_product(prefix)
+                .and_then(|p| p.checked_mul(last.checked_pow(rest)?))
+                .and_then(|p| p.checked_mul(target))
+                .is_some_and(|p| p <= number_max);
+            if fits {
+                Ordering::Equal
+            } else {
+                Ordering::Greater
             }
-        }
-    }
-
-    solutions
+        },
+    )
 }
 
 #[cfg(test)]
@@ -225,48 +409,166 @@ mod tests {
         assert_eq!(prime_factors(60), vec![2, 2, 3, 5]);
         assert_eq!(prime_factors(60), vec![2, 2, 3, 5]);
         assert_eq!(prime_factors(93), vec![3, 31]);
+        assert_eq!(prime_factors(1), Vec::<u64>::new());
+        assert_eq!(
+            prime_factors(18446744073709551557),
+            vec![18446744073709551557]
+        );
+        assert_eq!(
+            prime_factors(4294967279 * 4294967291),
+            vec![4294967279, 4294967291]
+        );
+        assert_eq!(
+            prime_factors(4294967291 * 4294967291),
+            vec![4294967291, 4294967291]
+        );
+        assert_eq!(
+            prime_factors(1 << 63),
+            repeat_n(2, 63).collect::<Vec<u64>>()
+        );
+        assert_eq!(
+            prime_factors(997 * 997 * 1_000_003 * 1_000_003),
+            vec![997, 997, 1_000_003, 1_000_003]
+        );
+    }
+
+    #[test]
+    fn factors_test() {
+        assert_eq!(factors(1), vec![1]);
+        assert_eq!(factors(7), vec![1, 7]);
+        assert_eq!(factors(12), vec![1, 2, 3, 4, 6, 12]);
+        assert_eq!(factors(72), vec![1, 2, 3, 4, 6, 8, 9, 12, 18, 24, 36, 72]);
+        assert_eq!(factors(1 << 20).len(), 21);
+        assert_eq!(factors(4294967279 * 4294967291).len(), 4);
     }
 
     #[test]
     fn add_enumerator_test() {
-        assert_eq!(add_enumerator(3, 2, 3), vec![vec![1, 2]]);
-        assert_eq!(add_enumerator(4, 2, 3), vec![vec![1, 3], vec![2, 2]]);
-        assert_eq!(add_enumerator(4, 3, 3), vec![vec![1, 1, 2]]);
-        assert_eq!(add_enumerator(5, 3, 3), vec![vec![1, 1, 3], vec![1, 2, 2]]);
-        assert_eq!(add_enumerator(6, 3, 3), vec![vec![1, 2, 3], vec![2, 2, 2]]);
         assert_eq!(
-            add_enumerator(6, 3, 4),
+            add_enumerator(1, 2, 3).collect::<Vec<_>>(),
+            Vec::<Vec<u64>>::new()
+        );
+        assert_eq!(add_enumerator(2, 1, 3).collect::<Vec<_>>(), vec![vec![2]]);
+        assert_eq!(
+            add_enumerator(4, 1, 3).collect::<Vec<_>>(),
+            Vec::<Vec<u64>>::new()
+        );
+        assert_eq!(
+            add_enumerator(3, 2, 3).collect::<Vec<_>>(),
+            vec![vec![1, 2]]
+        );
+        assert_eq!(
+            add_enumerator(4, 2, 3).collect::<Vec<_>>(),
+            vec![vec![1, 3], vec![2, 2]]
+        );
+        assert_eq!(
+            add_enumerator(4, 3, 3).collect::<Vec<_>>(),
+            vec![vec![1, 1, 2]]
+        );
+        assert_eq!(
+            add_enumerator(5, 3, 3).collect::<Vec<_>>(),
+            vec![vec![1, 1, 3], vec![1, 2, 2]]
+        );
+        assert_eq!(
+            add_enumerator(6, 3, 3).collect::<Vec<_>>(),
+            vec![vec![1, 2, 3], vec![2, 2, 2]]
+        );
+        assert_eq!(
+            add_enumerator(6, 3, 4).collect::<Vec<_>>(),
             vec![vec![1, 1, 4], vec![1, 2, 3], vec![2, 2, 2]]
         );
+        assert_eq!(
+            add_enumerator(6, 4, 2).collect::<Vec<_>>(),
+            vec![vec![1, 1, 2, 2]]
+        );
+        assert_eq!(
+            add_enumerator(7, 3, 3).collect::<Vec<_>>(),
+            vec![vec![1, 3, 3], vec![2, 2, 3]]
+        );
+    }
+
+    #[test]
+    fn add_enumerator_lazy_test() {
+        assert_eq!(
+            add_enumerator(400, 40, 100).take(2).collect::<Vec<_>>(),
+            vec![
+                [vec![1; 36], vec![64, 100, 100, 100]].concat(),
+                [vec![1; 36], vec![65, 99, 100, 100]].concat()
+            ]
+        );
+        // Prefixes that cannot reach the target are not walked through
+        assert_eq!(add_enumerator(10_000, 60, 16).next(), None);
+    }
+
+    #[test]
+    fn add_enumeration_size_test() {
+        for (target, number_count, number_max) in
+            [(1, 2, 3), (2, 1, 3), (4, 2, 3), (6, 3, 4), (7, 3, 3)]
+        {
+            assert_eq!(
+                add_enumeration_size(target, number_count, number_max),
+                add_enumerator(target, number_count, number_max).count() as u64
+            );
+        }
+        assert_eq!(add_enumeration_size(34, 4, 16), 150);
+        assert_eq!(add_enumeration_size(1_000_000_000, 2, 9), 0);
+    }
+
+    #[test]
+    fn unique_add_enumerator_test() {
+        for (target, number_count, number_max) in [(6, 3, 3), (10, 3, 9), (17, 2, 9), (45, 9, 9)] {
+            assert_eq!(
+                unique_add_enumerator(target, number_count, number_max).collect::<Vec<_>>(),
+                add_enumerator(target, number_count, number_max)
+                    .filter(|s| s.iter().tuple_windows().all(|(a, b)| a < b))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                unique_add_enumeration_size(target, number_count, number_max),
+                unique_add_enumerator(target, number_count, number_max).count() as u64
+            );
+        }
+        assert_eq!(unique_add_enumerator(3, 3, 9).next(), None);
+        assert_eq!(unique_add_enumerator(6, 4, 3).next(), None);
+        assert_eq!(unique_add_enumeration_size(25, 5, 9), 12);
     }
 
     #[test]
     fn sub_enumerator_test() {
         assert_eq!(
-            sub_enumerator(3, 2, 6),
+            sub_enumerator(3, 2, 6).collect::<Vec<_>>(),
             vec![vec![1, 4], vec![2, 5], vec![3, 6]]
         );
-        assert_eq!(sub_enumerator(4, 3, 6), vec![vec![1, 1, 6]]);
-        assert_eq!(sub_enumerator(3, 3, 6), vec![vec![1, 1, 5], vec![1, 2, 6]]);
+        assert_eq!(
+            sub_enumerator(4, 3, 6).collect::<Vec<_>>(),
+            vec![vec![1, 1, 6]]
+        );
+        assert_eq!(
+            sub_enumerator(3, 3, 6).collect::<Vec<_>>(),
+            vec![vec![1, 1, 5], vec![1, 2, 6]]
+        );
     }
 
     #[test]
     fn mul_enumerator_test() {
-        assert_eq!(mul_enumerator(3, 2, 3), vec![vec![1, 3]]);
         assert_eq!(
-            mul_enumerator(12, 3, 6),
+            mul_enumerator(3, 2, 3).collect::<Vec<_>>(),
+            vec![vec![1, 3]]
+        );
+        assert_eq!(
+            mul_enumerator(12, 3, 6).collect::<Vec<_>>(),
             vec![vec![1, 2, 6], vec![1, 3, 4], vec![2, 2, 3]]
         );
         assert_eq!(
-            mul_enumerator(30, 3, 10),
+            mul_enumerator(30, 3, 10).collect::<Vec<_>>(),
             vec![vec![1, 3, 10], vec![1, 5, 6], vec![2, 3, 5]]
         );
         assert_eq!(
-            mul_enumerator(45, 3, 10),
+            mul_enumerator(45, 3, 10).collect::<Vec<_>>(),
             vec![vec![1, 5, 9], vec![3, 3, 5]]
         );
         assert_eq!(
-            mul_enumerator(60, 4, 10),
+            mul_enumerator(60, 4, 10).collect::<Vec<_>>(),
             vec![
                 [1, 1, 6, 10],
                 [1, 2, 3, 10],
@@ -275,17 +577,27 @@ mod tests {
                 [2, 2, 3, 5]
             ]
         );
+        assert_eq!(
+            mul_enumerator(4294967279 * 4294967291, 2, u64::MAX).collect::<Vec<_>>(),
+            vec![
+                vec![1, 4294967279 * 4294967291],
+                vec![4294967279, 4294967291]
+            ]
+        );
     }
 
     #[test]
     fn div_enumerator_test() {
-        assert_eq!(div_enumerator(3, 2, 6), vec![vec![1, 3], vec![2, 6]]);
         assert_eq!(
-            div_enumerator(2, 3, 6),
+            div_enumerator(3, 2, 6).collect::<Vec<_>>(),
+            vec![vec![1, 3], vec![2, 6]]
+        );
+        assert_eq!(
+            div_enumerator(2, 3, 6).collect::<Vec<_>>(),
             vec![vec![1, 1, 2], vec![1, 2, 4], vec![1, 3, 6]]
         );
         assert_eq!(
-            div_enumerator(3, 3, 10),
+            div_enumerator(3, 3, 10).collect::<Vec<_>>(),
             vec![vec![1, 1, 3], vec![1, 2, 6], vec![1, 3, 9]]
         );
     }
@@ -3,16 +3,30 @@ use std::fmt;
 
 use splr::SolverError;
 
+use crate::model::Cell;
+
 #[derive(Debug, PartialEq)]
 pub enum GridCspError {
     CellOutOfBound(usize, usize),
+    InactiveCell(usize, usize),
     ColumnOutOfBound(usize),
     RowOutOfBound(usize),
     SquareOutOfBound(usize, usize),
+    ValueOutOfBound(u64),
     ConstrainedGroupTooSmall,
     ConstrainedGroupTooBig,
+    InvalidCountBounds(usize, usize),
+    InvalidVisibleCount(u64),
+    CellsNotAdjacent(Cell, Cell),
     UnsupportedSudokuSize,
+    UnsupportedBinaryPuzzleSize,
+    UnsupportedConstraint,
+    UnsupportedGlobalRule,
+    ConstraintTooLarge,
+    InvalidWeight,
+    CostOverflow,
     TooManyVariables,
+    NoScope,
     SolverError(SolverError),
     NoSolution,
     UnexpectedSolution,
@@ -29,15 +43,39 @@ impl fmt::Display for GridCspError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             GridCspError::CellOutOfBound(x, y) => write!(f, "Cell ({x}, {y}) is out of bound"),
+            GridCspError::InactiveCell(x, y) => write!(f, "Cell ({x}, {y}) is inactive"),
             GridCspError::ColumnOutOfBound(x) => write!(f, "Column {x} is out of bound"),
             GridCspError::RowOutOfBound(y) => write!(f, "Row {y} is out of bound"),
             GridCspError::SquareOutOfBound(x, y) => {
                 write!(f, "Square corner ({x}, {y}) is out of bound")
             }
+            GridCspError::ValueOutOfBound(v) => write!(f, "Value {v} is out of bound"),
             GridCspError::ConstrainedGroupTooSmall => write!(f, "Constrained group is too small"),
             GridCspError::ConstrainedGroupTooBig => write!(f, "Constrained group is too big"),
+            GridCspError::InvalidCountBounds(min, max) => {
+                write!(f, "Count bounds {min}..={max} are empty")
+            }
+            GridCspError::InvalidVisibleCount(v) => {
+                write!(f, "Visible count {v} cannot be reached")
+            }
+            GridCspError::CellsNotAdjacent(a, b) => write!(
+                f,
+                "Cells ({}, {}) and ({}, {}) are not adjacent",
+                a.x, a.y, b.x, b.y
+            ),
             GridCspError::UnsupportedSudokuSize => write!(f, "Sudoku size is not a perfect square"),
+            GridCspError::UnsupportedBinaryPuzzleSize => {
+                write!(f, "Binary puzzle size is not even")
+            }
+            GridCspError::UnsupportedConstraint => {
+                write!(f, "Constraint is not supported on this grid")
+            }
+            GridCspError::UnsupportedGlobalRule => write!(f, "Global rule is not supported"),
+            GridCspError::ConstraintTooLarge => write!(f, "Constraint is too large to encode"),
+            GridCspError::InvalidWeight => write!(f, "Soft constraint weight must be positive"),
+            GridCspError::CostOverflow => write!(f, "Cost does not fit in 64 bits"),
             GridCspError::TooManyVariables => write!(f, "Problem has too many variables"),
+            GridCspError::NoScope => write!(f, "No scope to pop"),
             GridCspError::SolverError(err) => write!(f, "Solver error: {err}"),
             GridCspError::NoSolution => write!(f, "Problem has no solution"),
             GridCspError::UnexpectedSolution => write!(f, "Solver produced unexpected solution"),
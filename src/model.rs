@@ -1,18 +1,44 @@
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 
 use crate::error::GridCspError;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Constraint {
     Add(u64),
+    ArrowSum { circle: Cell },
+    Consecutive,
+    Count { value: u64, min: usize, max: usize },
     Div(u64),
     Equal(u64),
+    Line(LineConstraint),
+    MaxRun(usize),
     Mul(u64),
+    PairSum(u64),
+    Ratio(u64),
+    // Lengths of the shaded runs of a boolean group, in order
+    Runs(Vec<usize>),
+    Sandwich { low: u64, high: u64, sum: u64 },
     Sub(u64),
     Unique,
+    UniqueAdd(u64),
+    Visible(u64),
 }
 
+// Constraints along an ordered group of cells
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineConstraint {
+    // Inner cells are strictly between the values of both ends
+    Between,
+    Palindrome,
+    // Distinct consecutive values in any order
+    Renban,
+    // Adjacent cells differ by at least the given amount
+    Whisper(u64),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Cell {
     pub x: usize,
     pub y: usize,
@@ -22,6 +48,8 @@ pub struct Cell {
 pub enum CellGroup {
     Column(usize),
     Row(usize),
+    ReversedColumn(usize),
+    ReversedRow(usize),
     Square {
         x: usize,
         y: usize,
@@ -35,12 +63,62 @@ pub enum CellGroup {
 pub struct ConstrainedGroup {
     pub constraint: Constraint,
     pub group: CellGroup,
+    // Soft constraints may be violated at the cost of their weight
+    #[serde(default)]
+    pub weight: Option<u64>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Adjacency {
+    King,
+    Orthogonal,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GlobalRule {
+    AntiKing,
+    AntiKnight,
+    // The cells holding the value form one orthogonally connected region
+    Connected(u64),
+    // Every value but the largest one has its successor in an adjacent cell
+    ConsecutivePath(Adjacency),
+    DistinctColumns,
+    DistinctRows,
+    // Orthogonally adjacent cells without a pairwise constraint must not satisfy this one
+    Negative(Constraint),
+    NonConsecutiveOrthogonal,
+    // The value never appears in two cells touching each other, even diagonally
+    NoTouching(u64),
+}
+
+// Linear objective over the values of the cells
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Objective {
+    Maximize(Vec<ObjectiveTerm>),
+    Minimize(Vec<ObjectiveTerm>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ObjectiveTerm {
+    // Weight times the value of each cell of the group
+    Values {
+        group: CellGroup,
+        weight: i64,
+    },
+    // Weight for each cell of the group holding the value
+    Count {
+        group: CellGroup,
+        value: u64,
+        weight: i64,
+    },
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GridDimensions {
     pub width: usize,
     pub height: usize,
+    #[serde(default = "GridDimensions::default_number_min")]
+    pub number_min: u64,
     pub number_max: u64,
 }
 
@@ -48,6 +126,10 @@ pub struct GridDimensions {
 pub struct GenericProblem {
     pub grid: GridDimensions,
     pub constraints: Vec<ConstrainedGroup>,
+    #[serde(default)]
+    pub rules: Vec<GlobalRule>,
+    #[serde(default)]
+    pub inactive: HashSet<Cell>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -56,12 +138,63 @@ pub struct SudokuProblem {
     pub constraints: Vec<ConstrainedGroup>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiSudokuProblem {
+    pub grid_size: usize,
+    pub offsets: Vec<Cell>,
+    pub constraints: Vec<ConstrainedGroup>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct KenKenProblem {
     pub grid_size: usize,
     pub constraints: Vec<ConstrainedGroup>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkyscrapersProblem {
+    pub grid_size: usize,
+    pub constraints: Vec<ConstrainedGroup>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KakuroProblem {
+    pub width: usize,
+    pub height: usize,
+    pub blocked: Vec<Cell>,
+    pub constraints: Vec<ConstrainedGroup>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HidatoProblem {
+    pub width: usize,
+    pub height: usize,
+    pub adjacency: Adjacency,
+    pub blocked: Vec<Cell>,
+    pub constraints: Vec<ConstrainedGroup>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BinaryPuzzleProblem {
+    pub grid_size: usize,
+    pub constraints: Vec<ConstrainedGroup>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StarBattleProblem {
+    pub grid_size: usize,
+    pub stars: usize,
+    pub regions: Vec<CellGroup>,
+    pub constraints: Vec<ConstrainedGroup>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NonogramProblem {
+    pub rows: Vec<Vec<usize>>,
+    pub columns: Vec<Vec<usize>>,
+    pub constraints: Vec<ConstrainedGroup>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MagicSquareProblem {
     pub grid_size: usize,
@@ -72,6 +205,62 @@ impl Constraint {
     pub fn over(self, group: CellGroup) -> ConstrainedGroup {
         ConstrainedGroup::new(self, group)
     }
+
+    pub fn exactly(value: u64, count: usize) -> Self {
+        Constraint::Count {
+            value,
+            min: count,
+            max: count,
+        }
+    }
+
+    pub fn at_most(value: u64, count: usize) -> Self {
+        Constraint::Count {
+            value,
+            min: 0,
+            max: count,
+        }
+    }
+
+    pub fn at_least(value: u64, count: usize) -> Self {
+        Constraint::Count {
+            value,
+            min: count,
+            max: usize::MAX,
+        }
+    }
+
+    pub fn is_pairwise(&self) -> bool {
+        matches!(
+            self,
+            Constraint::Consecutive | Constraint::PairSum(_) | Constraint::Ratio(_)
+        )
+    }
+
+    // Arithmetic constraints enumerate values starting from 1
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            Constraint::Add(_)
+                | Constraint::ArrowSum { .. }
+                | Constraint::Div(_)
+                | Constraint::Mul(_)
+                | Constraint::Sandwich { .. }
+                | Constraint::Sub(_)
+                | Constraint::UniqueAdd(_)
+        )
+    }
+
+    pub fn pair_holds(&self, a: u64, b: u64) -> bool {
+        match self {
+            Constraint::Consecutive => a.abs_diff(b) == 1,
+            Constraint::PairSum(sum) => a.checked_add(b) == Some(*sum),
+            Constraint::Ratio(ratio) => {
+                a.checked_mul(*ratio) == Some(b) || b.checked_mul(*ratio) == Some(a)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl Cell {
@@ -93,6 +282,10 @@ impl CellGroup {
         match self {
             CellGroup::Column(x) => (0..grid.height).map(|y| Cell::new(*x, y)).collect(),
             CellGroup::Row(y) => (0..grid.width).map(|x| Cell::new(x, *y)).collect(),
+            CellGroup::ReversedColumn(x) => {
+                (0..grid.height).rev().map(|y| Cell::new(*x, y)).collect()
+            }
+            CellGroup::ReversedRow(y) => (0..grid.width).rev().map(|x| Cell::new(x, *y)).collect(),
             CellGroup::Square {
                 x,
                 y,
@@ -109,23 +302,19 @@ impl CellGroup {
         ConstrainedGroup::new(constrain, self)
     }
 
-    pub fn size(&self, grid: GridDimensions) -> usize {
-        match self {
-            CellGroup::Column(_) => grid.height,
-            CellGroup::Row(_) => grid.width,
-            CellGroup::Square { height, width, .. } => height * width,
-            CellGroup::List(cells) => cells.len(),
-        }
+    // Number of active cells in the group
+    pub fn size(&self, problem: &GenericProblem) -> usize {
+        problem.group_cells(self).len()
     }
 
     fn validate(&self, grid: GridDimensions) -> Result<(), GridCspError> {
         match self {
-            CellGroup::Column(x) => {
+            CellGroup::Column(x) | CellGroup::ReversedColumn(x) => {
                 if *x >= grid.width {
                     return Err(GridCspError::ColumnOutOfBound(*x));
                 }
             }
-            CellGroup::Row(y) => {
+            CellGroup::Row(y) | CellGroup::ReversedRow(y) => {
                 if *y >= grid.height {
                     return Err(GridCspError::RowOutOfBound(*y));
                 }
@@ -150,23 +339,210 @@ impl CellGroup {
 
 impl ConstrainedGroup {
     pub fn new(constraint: Constraint, group: CellGroup) -> Self {
-        ConstrainedGroup { constraint, group }
+        ConstrainedGroup {
+            constraint,
+            group,
+            weight: None,
+        }
     }
 
-    fn validate(&self, grid: GridDimensions) -> Result<(), GridCspError> {
+    pub fn with_weight(mut self, weight: u64) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn validate(&self, problem: &GenericProblem) -> Result<(), GridCspError> {
+        let grid = problem.grid;
+        if self.weight == Some(0) {
+            return Err(GridCspError::InvalidWeight);
+        }
         self.group.validate(grid)?;
-        match self.constraint {
+        let cells = problem.group_cells(&self.group);
+        if let Some(cell) = cells.iter().find(|c| !problem.is_active(**c)) {
+            return Err(GridCspError::InactiveCell(cell.x, cell.y));
+        }
+        let size = cells.len();
+        if self.constraint.is_arithmetic() && grid.number_min != 1 {
+            return Err(GridCspError::UnsupportedConstraint);
+        }
+        match &self.constraint {
             Constraint::Add(_) | Constraint::Div(_) | Constraint::Mul(_) | Constraint::Sub(_) => {
-                if self.group.size(grid) < 2 {
+                if size < 2 {
+                    return Err(GridCspError::ConstrainedGroupTooSmall);
+                }
+            }
+            Constraint::ArrowSum { circle } => {
+                circle.validate(grid)?;
+                if !problem.is_active(*circle) {
+                    return Err(GridCspError::InactiveCell(circle.x, circle.y));
+                }
+                if size < 1 {
+                    return Err(GridCspError::ConstrainedGroupTooSmall);
+                }
+            }
+            Constraint::Count { value, min, max } => {
+                grid.validate_value(*value)?;
+                if min > max {
+                    return Err(GridCspError::InvalidCountBounds(*min, *max));
+                } else if *min > size {
+                    return Err(GridCspError::ConstrainedGroupTooSmall);
+                }
+            }
+            Constraint::Equal(value) => grid.validate_value(*value)?,
+            Constraint::Line(line) => {
+                let min_size = match line {
+                    LineConstraint::Between => 3,
+                    LineConstraint::Palindrome | LineConstraint::Whisper(_) => 2,
+                    LineConstraint::Renban => 1,
+                };
+                if size < min_size {
+                    return Err(GridCspError::ConstrainedGroupTooSmall);
+                } else if *line == LineConstraint::Renban && size as u64 > grid.number_count() {
+                    return Err(GridCspError::ConstrainedGroupTooBig);
+                }
+            }
+            Constraint::MaxRun(length) => {
+                if *length == 0 {
+                    return Err(GridCspError::ConstrainedGroupTooSmall);
+                }
+            }
+            Constraint::Visible(visible) => {
+                // At least the first cell is seen, and only strictly increasing heights after it
+                if *visible == 0 || *visible > (size as u64).min(grid.number_count()) {
+                    return Err(GridCspError::InvalidVisibleCount(*visible));
+                }
+            }
+            Constraint::Consecutive | Constraint::PairSum(_) | Constraint::Ratio(_) => match size {
+                0 | 1 => return Err(GridCspError::ConstrainedGroupTooSmall),
+                2 => {
+                    if cells[0].x.abs_diff(cells[1].x) + cells[0].y.abs_diff(cells[1].y) != 1 {
+                        return Err(GridCspError::CellsNotAdjacent(cells[0], cells[1]));
+                    }
+                }
+                _ => return Err(GridCspError::ConstrainedGroupTooBig),
+            },
+            Constraint::Runs(runs) => {
+                if grid.number_min != 0 || grid.number_max != 1 {
+                    return Err(GridCspError::UnsupportedConstraint);
+                }
+                let runs: Vec<usize> = runs.iter().copied().filter(|r| *r > 0).collect();
+                if runs.iter().sum::<usize>() + runs.len().saturating_sub(1) > size {
+                    return Err(GridCspError::ConstrainedGroupTooSmall);
+                }
+            }
+            Constraint::Sandwich { low, high, .. } => {
+                grid.validate_value(*low)?;
+                grid.validate_value(*high)?;
+                if size < 2 {
                     return Err(GridCspError::ConstrainedGroupTooSmall);
                 }
             }
-            Constraint::Equal(_) => {}
             Constraint::Unique => {
-                if self.group.size(grid) as u64 > grid.number_max {
+                if size as u64 > grid.number_count() {
                     return Err(GridCspError::ConstrainedGroupTooBig);
                 }
             }
+            Constraint::UniqueAdd(_) => {
+                if size < 2 {
+                    return Err(GridCspError::ConstrainedGroupTooSmall);
+                } else if size as u64 > grid.number_count() {
+                    return Err(GridCspError::ConstrainedGroupTooBig);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GlobalRule {
+    fn validate(&self, grid: GridDimensions) -> Result<(), GridCspError> {
+        match self {
+            GlobalRule::Negative(constraint) => {
+                if !constraint.is_pairwise() {
+                    return Err(GridCspError::UnsupportedGlobalRule);
+                }
+            }
+            GlobalRule::Connected(value) | GlobalRule::NoTouching(value) => {
+                grid.validate_value(*value)?
+            }
+            GlobalRule::AntiKing
+            | GlobalRule::AntiKnight
+            | GlobalRule::ConsecutivePath(_)
+            | GlobalRule::DistinctColumns
+            | GlobalRule::DistinctRows
+            | GlobalRule::NonConsecutiveOrthogonal => {}
+        }
+        Ok(())
+    }
+}
+
+impl Objective {
+    pub fn terms(&self) -> &[ObjectiveTerm] {
+        match self {
+            Objective::Maximize(terms) | Objective::Minimize(terms) => terms,
+        }
+    }
+
+    pub fn validate(&self, problem: &GenericProblem) -> Result<(), GridCspError> {
+        self.terms().iter().try_for_each(|t| t.validate(problem))
+    }
+
+    // Objective value of a solution, inactive cells not counting
+    pub fn evaluate(
+        &self,
+        problem: &GenericProblem,
+        solution: &[Vec<Option<u64>>],
+    ) -> Result<i64, GridCspError> {
+        self.terms()
+            .iter()
+            .flat_map(|term| {
+                problem
+                    .group_cells(term.group())
+                    .into_iter()
+                    .filter_map(|c| solution[c.x][c.y])
+                    .map(|value| term.weight(value))
+            })
+            .try_fold(0i64, |acc, weight| {
+                acc.checked_add(weight?).ok_or(GridCspError::CostOverflow)
+            })
+    }
+}
+
+impl ObjectiveTerm {
+    pub fn group(&self) -> &CellGroup {
+        match self {
+            ObjectiveTerm::Values { group, .. } | ObjectiveTerm::Count { group, .. } => group,
+        }
+    }
+
+    pub fn weight(&self, value: u64) -> Result<i64, GridCspError> {
+        match *self {
+            ObjectiveTerm::Values { weight, .. } => i64::try_from(value)
+                .ok()
+                .and_then(|value| weight.checked_mul(value))
+                .ok_or(GridCspError::CostOverflow),
+            ObjectiveTerm::Count {
+                value: counted,
+                weight,
+                ..
+            } => {
+                if value == counted {
+                    Ok(weight)
+                } else {
+                    Ok(0)
+                }
+            }
+        }
+    }
+
+    fn validate(&self, problem: &GenericProblem) -> Result<(), GridCspError> {
+        self.group().validate(problem.grid)?;
+        let cells = problem.group_cells(self.group());
+        if let Some(cell) = cells.iter().find(|c| !problem.is_active(**c)) {
+            return Err(GridCspError::InactiveCell(cell.x, cell.y));
+        }
+        if let ObjectiveTerm::Count { value, .. } = self {
+            problem.grid.validate_value(*value)?;
         }
         Ok(())
     }
@@ -177,9 +553,31 @@ impl GridDimensions {
         GridDimensions {
             width,
             height,
+            number_min: Self::default_number_min(),
             number_max,
         }
     }
+
+    pub fn with_number_min(mut self, number_min: u64) -> Self {
+        self.number_min = number_min;
+        self
+    }
+
+    pub fn number_count(&self) -> u64 {
+        (self.number_max + 1).saturating_sub(self.number_min)
+    }
+
+    fn default_number_min() -> u64 {
+        1
+    }
+
+    fn validate_value(&self, value: u64) -> Result<(), GridCspError> {
+        if value < self.number_min || value > self.number_max {
+            Err(GridCspError::ValueOutOfBound(value))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl GenericProblem {
@@ -187,6 +585,42 @@ impl GenericProblem {
         GenericProblem {
             grid,
             constraints: Vec::new(),
+            rules: Vec::new(),
+            inactive: HashSet::new(),
+        }
+    }
+
+    pub fn deactivate(&mut self, cell: Cell) {
+        self.inactive.insert(cell);
+    }
+
+    pub fn set_active_cells(&mut self, active: impl AsRef<[Cell]>) {
+        let active: HashSet<Cell> = active.as_ref().iter().copied().collect();
+        self.inactive = self.all_cells().filter(|c| !active.contains(c)).collect();
+    }
+
+    pub fn is_active(&self, cell: Cell) -> bool {
+        !self.inactive.contains(&cell)
+    }
+
+    pub fn active_cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        self.all_cells().filter(|c| self.is_active(*c))
+    }
+
+    fn all_cells(&self) -> impl Iterator<Item = Cell> + use<> {
+        let height = self.grid.height;
+        (0..self.grid.width).flat_map(move |x| (0..height).map(move |y| Cell::new(x, y)))
+    }
+
+    // Shaped groups only cover active cells, while listed cells are kept as is
+    pub fn group_cells(&self, group: &CellGroup) -> Vec<Cell> {
+        match group {
+            CellGroup::List(cells) => cells.clone(),
+            _ => group
+                .to_cells(self.grid)
+                .into_iter()
+                .filter(|c| self.is_active(*c))
+                .collect(),
         }
     }
 
@@ -194,10 +628,16 @@ impl GenericProblem {
         self.constraints.push(constraint)
     }
 
+    pub fn add_rule(&mut self, rule: GlobalRule) {
+        self.rules.push(rule)
+    }
+
     pub fn validate(&self) -> Result<(), GridCspError> {
-        self.constraints
+        self.inactive
             .iter()
-            .try_for_each(|c| c.validate(self.grid))
+            .try_for_each(|c| c.validate(self.grid))?;
+        self.constraints.iter().try_for_each(|c| c.validate(self))?;
+        self.rules.iter().try_for_each(|r| r.validate(self.grid))
     }
 }
 
@@ -251,6 +691,71 @@ impl TryFrom<SudokuProblem> for GenericProblem {
     }
 }
 
+impl MultiSudokuProblem {
+    pub fn new(grid_size: usize) -> Self {
+        MultiSudokuProblem {
+            grid_size,
+            offsets: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn samurai(grid_size: usize) -> Self {
+        let mut problem = MultiSudokuProblem::new(grid_size);
+        let far = 2 * (grid_size - grid_size.isqrt());
+        let center = far / 2;
+        for offset in [(0, 0), (far, 0), (center, center), (0, far), (far, far)] {
+            problem.add_grid(Cell::new(offset.0, offset.1));
+        }
+        problem
+    }
+
+    pub fn add_grid(&mut self, offset: Cell) {
+        self.offsets.push(offset)
+    }
+
+    pub fn add_constraint(&mut self, constraint: ConstrainedGroup) {
+        self.constraints.push(constraint)
+    }
+}
+
+impl TryFrom<MultiSudokuProblem> for GenericProblem {
+    type Error = GridCspError;
+
+    fn try_from(mut problem: MultiSudokuProblem) -> Result<Self, Self::Error> {
+        let sudoku = GenericProblem::try_from(SudokuProblem::new(problem.grid_size))?;
+        let width = problem.offsets.iter().map(|o| o.x).max().unwrap_or(0) + problem.grid_size;
+        let height = problem.offsets.iter().map(|o| o.y).max().unwrap_or(0) + problem.grid_size;
+        let mut generic =
+            GenericProblem::new(GridDimensions::new(width, height, problem.grid_size as u64));
+
+        let mut active = Vec::new();
+        // Overlapping grids share some of their groups, which only need to be added once
+        let mut groups = HashSet::new();
+        for offset in problem.offsets.iter() {
+            let translate = |c: Cell| Cell::new(c.x + offset.x, c.y + offset.y);
+            active.extend(sudoku.all_cells().map(translate));
+            for cg in sudoku.constraints.iter() {
+                let cells: Vec<Cell> = sudoku
+                    .group_cells(&cg.group)
+                    .into_iter()
+                    .map(translate)
+                    .collect();
+                if groups.insert(cells.clone()) {
+                    generic.add_constraint(cg.constraint.clone().over(CellGroup::List(cells)));
+                }
+            }
+        }
+        generic.set_active_cells(active);
+        problem
+            .constraints
+            .drain(..)
+            .for_each(|cst| generic.add_constraint(cst));
+
+        Ok(generic)
+    }
+}
+
 impl KenKenProblem {
     pub fn new(grid_size: usize) -> Self {
         KenKenProblem {
@@ -285,6 +790,243 @@ impl From<KenKenProblem> for GenericProblem {
     }
 }
 
+impl SkyscrapersProblem {
+    pub fn new(grid_size: usize) -> Self {
+        SkyscrapersProblem {
+            grid_size,
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn add_constraint(&mut self, constraint: ConstrainedGroup) {
+        self.constraints.push(constraint)
+    }
+}
+
+impl From<SkyscrapersProblem> for GenericProblem {
+    fn from(problem: SkyscrapersProblem) -> Self {
+        // Skyscrapers share the latin square structure of KenKen, only the clues differ
+        GenericProblem::from(KenKenProblem {
+            grid_size: problem.grid_size,
+            constraints: problem.constraints,
+        })
+    }
+}
+
+impl KakuroProblem {
+    pub fn new(width: usize, height: usize) -> Self {
+        KakuroProblem {
+            width,
+            height,
+            blocked: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn block(&mut self, cell: Cell) {
+        self.blocked.push(cell)
+    }
+
+    pub fn add_run(&mut self, cells: Vec<Cell>, sum: u64) {
+        self.add_constraint(Constraint::UniqueAdd(sum).over(CellGroup::List(cells)))
+    }
+
+    pub fn add_constraint(&mut self, constraint: ConstrainedGroup) {
+        self.constraints.push(constraint)
+    }
+}
+
+impl From<KakuroProblem> for GenericProblem {
+    fn from(mut problem: KakuroProblem) -> Self {
+        let mut generic =
+            GenericProblem::new(GridDimensions::new(problem.width, problem.height, 9));
+        problem
+            .blocked
+            .drain(..)
+            .for_each(|cell| generic.deactivate(cell));
+        problem
+            .constraints
+            .drain(..)
+            .for_each(|cst| generic.add_constraint(cst));
+
+        generic
+    }
+}
+
+impl HidatoProblem {
+    pub fn new(width: usize, height: usize) -> Self {
+        HidatoProblem {
+            width,
+            height,
+            adjacency: Adjacency::King,
+            blocked: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn numbrix(width: usize, height: usize) -> Self {
+        HidatoProblem {
+            adjacency: Adjacency::Orthogonal,
+            ..HidatoProblem::new(width, height)
+        }
+    }
+
+    pub fn block(&mut self, cell: Cell) {
+        self.blocked.push(cell)
+    }
+
+    pub fn add_constraint(&mut self, constraint: ConstrainedGroup) {
+        self.constraints.push(constraint)
+    }
+}
+
+impl From<HidatoProblem> for GenericProblem {
+    fn from(mut problem: HidatoProblem) -> Self {
+        let mut generic =
+            GenericProblem::new(GridDimensions::new(problem.width, problem.height, 0));
+        problem
+            .blocked
+            .drain(..)
+            .for_each(|cell| generic.deactivate(cell));
+        // Every active cell holds a distinct number from 1 to the number of active cells
+        generic.grid.number_max = generic.active_cells().count() as u64;
+        generic.add_constraint(Constraint::Unique.over(CellGroup::Square {
+            x: 0,
+            y: 0,
+            height: problem.height,
+            width: problem.width,
+        }));
+        generic.add_rule(GlobalRule::ConsecutivePath(problem.adjacency));
+        problem
+            .constraints
+            .drain(..)
+            .for_each(|cst| generic.add_constraint(cst));
+
+        generic
+    }
+}
+
+impl BinaryPuzzleProblem {
+    pub fn new(grid_size: usize) -> Self {
+        BinaryPuzzleProblem {
+            grid_size,
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn add_constraint(&mut self, constraint: ConstrainedGroup) {
+        self.constraints.push(constraint)
+    }
+}
+
+impl TryFrom<BinaryPuzzleProblem> for GenericProblem {
+    type Error = GridCspError;
+
+    fn try_from(mut problem: BinaryPuzzleProblem) -> Result<Self, Self::Error> {
+        if !problem.grid_size.is_multiple_of(2) {
+            return Err(GridCspError::UnsupportedBinaryPuzzleSize);
+        }
+
+        let half = problem.grid_size / 2;
+        let mut generic = GenericProblem::new(
+            GridDimensions::new(problem.grid_size, problem.grid_size, 1).with_number_min(0),
+        );
+        for i in 0..problem.grid_size {
+            for group in [CellGroup::Row(i), CellGroup::Column(i)] {
+                generic.add_constraint(Constraint::exactly(1, half).over(group.clone()));
+                generic.add_constraint(Constraint::MaxRun(2).over(group));
+            }
+        }
+        generic.add_rule(GlobalRule::DistinctRows);
+        generic.add_rule(GlobalRule::DistinctColumns);
+        problem
+            .constraints
+            .drain(..)
+            .for_each(|cst| generic.add_constraint(cst));
+
+        Ok(generic)
+    }
+}
+
+impl StarBattleProblem {
+    pub fn new(grid_size: usize, stars: usize) -> Self {
+        StarBattleProblem {
+            grid_size,
+            stars,
+            regions: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn add_region(&mut self, region: CellGroup) {
+        self.regions.push(region)
+    }
+
+    pub fn add_constraint(&mut self, constraint: ConstrainedGroup) {
+        self.constraints.push(constraint)
+    }
+}
+
+impl From<StarBattleProblem> for GenericProblem {
+    fn from(mut problem: StarBattleProblem) -> Self {
+        // Shaded cells hold a star and are set to 1, the other ones are set to 0
+        let mut generic = GenericProblem::new(
+            GridDimensions::new(problem.grid_size, problem.grid_size, 1).with_number_min(0),
+        );
+        let stars = Constraint::exactly(1, problem.stars);
+        for i in 0..problem.grid_size {
+            generic.add_constraint(stars.clone().over(CellGroup::Row(i)));
+            generic.add_constraint(stars.clone().over(CellGroup::Column(i)));
+        }
+        problem
+            .regions
+            .drain(..)
+            .for_each(|region| generic.add_constraint(stars.clone().over(region)));
+        generic.add_rule(GlobalRule::NoTouching(1));
+        problem
+            .constraints
+            .drain(..)
+            .for_each(|cst| generic.add_constraint(cst));
+
+        generic
+    }
+}
+
+impl NonogramProblem {
+    pub fn new(rows: Vec<Vec<usize>>, columns: Vec<Vec<usize>>) -> Self {
+        NonogramProblem {
+            rows,
+            columns,
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn add_constraint(&mut self, constraint: ConstrainedGroup) {
+        self.constraints.push(constraint)
+    }
+}
+
+impl From<NonogramProblem> for GenericProblem {
+    fn from(mut problem: NonogramProblem) -> Self {
+        // Shaded cells are set to 1, blank ones to 0
+        let mut generic = GenericProblem::new(
+            GridDimensions::new(problem.columns.len(), problem.rows.len(), 1).with_number_min(0),
+        );
+        for (y, runs) in problem.rows.drain(..).enumerate() {
+            generic.add_constraint(Constraint::Runs(runs).over(CellGroup::Row(y)));
+        }
+        for (x, runs) in problem.columns.drain(..).enumerate() {
+            generic.add_constraint(Constraint::Runs(runs).over(CellGroup::Column(x)));
+        }
+        problem
+            .constraints
+            .drain(..)
+            .for_each(|cst| generic.add_constraint(cst));
+
+        generic
+    }
+}
+
 impl MagicSquareProblem {
     pub fn new(grid_size: usize) -> Self {
         MagicSquareProblem {
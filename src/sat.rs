@@ -1,42 +1,343 @@
 use crate::constraints::*;
 use crate::error::GridCspError;
-use crate::model::{Cell, GenericProblem, GridDimensions};
+use crate::model::{
+    Adjacency, Cell, CellGroup, ConstrainedGroup, Constraint, GenericProblem, GlobalRule,
+    GridDimensions, LineConstraint, Objective,
+};
 
 use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 
 use itertools::Itertools;
-use splr::{Certificate, Config, SolveIF, Solver};
+use splr::{Certificate, Config, SatSolverIF, SolveIF, Solver, SolverError};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AmoEncoding {
+    // Pairwise for small groups, sequential counter for larger ones
+    #[default]
+    Auto,
+    Bimander,
+    Commander,
+    Pairwise,
+    Product,
+    Sequential,
+}
+
+// How the value of each cell is represented, value vars being derived on demand when the
+// encoding is not one-hot
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    // Value vars with an exactly one constraint
+    #[default]
+    OneHot,
+    // One var per value but the smallest, true iff the cell is at least that value
+    Order,
+    // Bits of the value offset from the smallest value
+    Log,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SumEncoding {
+    // Totalizer once the enumeration has more alternatives than the limit, and adder network
+    // once the totalizer gets too large
+    #[default]
+    Auto,
+    Enumeration,
+    Totalizer,
+    Adder,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SolverOptions {
+    pub amo_encoding: AmoEncoding,
+    pub encoding: Encoding,
+    pub sum_encoding: SumEncoding,
+    // Largest number of alternatives a single constraint may enumerate
+    pub enumeration_limit: usize,
+    // Most clauses a totalizer may take, sums switching to an adder network beyond it
+    pub totalizer_limit: u64,
+}
+
+const SUM_ENUMERATION_LIMIT: u64 = 100;
+
+// Popped scopes the persistent solver may hold before it is rebuilt without them
+const DEAD_SCOPE_LIMIT: usize = 100;
+
+// Optimal solution along with its objective value, only returned once no better solution is left
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Optimum {
+    pub solution: Vec<Vec<Option<u64>>>,
+    pub value: i64,
+}
+
+// Solution violating the least weight of soft constraints, listed by their index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SoftSolution {
+    pub solution: Vec<Vec<Option<u64>>>,
+    pub violated: Vec<usize>,
+    pub violated_weight: u64,
+}
 
 pub struct GridCspSolver {
     var_count: i32,
-    grid_vars: Vec<Vec<Vec<i32>>>,
+    number_min: u64,
+    number_max: u64,
+    options: SolverOptions,
+    grid_vars: Vec<Vec<Option<Vec<i32>>>>,
+    value_vars: HashMap<(Cell, u64), i32>,
+    at_least_vars: HashMap<(Cell, u64), i32>,
+    offset_bits: HashMap<Cell, Vec<i32>>,
     clauses: Vec<Vec<i32>>,
+    // Solver kept across calls along with how many clauses it was given
+    solver: Option<Solver>,
+    loaded_clauses: usize,
+    // Popped scopes whose clauses the solver still holds
+    dead_scopes: usize,
+    // Problem encoded so far, constraints included
+    problem: GenericProblem,
+    // Activation var of each open scope along with the constraint and clause counts when it was
+    // pushed
+    scopes: Vec<(i32, usize, usize)>,
+    // Index of each soft constraint along with the var relaxing it and its weight
+    soft_constraints: Vec<(usize, i32, u64)>,
+    // Var relaxing the soft constraint being encoded
+    relaxation: Option<i32>,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        SolverOptions {
+            amo_encoding: AmoEncoding::default(),
+            encoding: Encoding::default(),
+            sum_encoding: SumEncoding::default(),
+            enumeration_limit: 100_000,
+            totalizer_limit: 100_000,
+        }
+    }
+}
+
+impl SolverOptions {
+    pub fn with_amo_encoding(mut self, amo_encoding: AmoEncoding) -> Self {
+        self.amo_encoding = amo_encoding;
+        self
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_sum_encoding(mut self, sum_encoding: SumEncoding) -> Self {
+        self.sum_encoding = sum_encoding;
+        self
+    }
+
+    pub fn with_enumeration_limit(mut self, enumeration_limit: usize) -> Self {
+        self.enumeration_limit = enumeration_limit;
+        self
+    }
+
+    pub fn with_totalizer_limit(mut self, totalizer_limit: u64) -> Self {
+        self.totalizer_limit = totalizer_limit;
+        self
+    }
 }
 
 impl GridCspSolver {
     pub fn new(grid: GridDimensions) -> Result<Self, GridCspError> {
+        GridCspSolver::with_inactive_cells(grid, [])
+    }
+
+    pub fn with_inactive_cells(
+        grid: GridDimensions,
+        inactive: impl IntoIterator<Item = Cell>,
+    ) -> Result<Self, GridCspError> {
+        GridCspSolver::with_options(grid, inactive, SolverOptions::default())
+    }
+
+    pub fn with_options(
+        grid: GridDimensions,
+        inactive: impl IntoIterator<Item = Cell>,
+        options: SolverOptions,
+    ) -> Result<Self, GridCspError> {
         let mut this = GridCspSolver {
             var_count: 0,
+            number_min: grid.number_min,
+            number_max: grid.number_max,
+            options,
             grid_vars: Vec::with_capacity(grid.width),
+            value_vars: HashMap::new(),
+            at_least_vars: HashMap::new(),
+            offset_bits: HashMap::new(),
             clauses: Vec::new(),
+            solver: None,
+            loaded_clauses: 0,
+            dead_scopes: 0,
+            problem: GenericProblem {
+                inactive: inactive.into_iter().collect(),
+                ..GenericProblem::new(grid)
+            },
+            scopes: Vec::new(),
+            soft_constraints: Vec::new(),
+            relaxation: None,
         };
-        // Generate cell vars
+        // Generate cell vars, inactive cells get none
+        let count = grid.number_count() as usize;
         for x in 0..grid.width {
             this.grid_vars.push(Vec::with_capacity(grid.height));
-            for _y in 0..grid.height {
-                let vars: Vec<i32> = (0..grid.number_max)
-                    .map(|_| this.alloc_var().unwrap())
-                    .collect();
-                this.add_exactly_one_clause(&vars)?;
-                this.grid_vars[x].push(vars);
+            for y in 0..grid.height {
+                if !this.problem.is_active(Cell::new(x, y)) {
+                    this.grid_vars[x].push(None);
+                    continue;
+                }
+                let vars = match options.encoding {
+                    Encoding::OneHot => {
+                        let vars: Vec<i32> = (0..count).map(|_| this.alloc_var()).try_collect()?;
+                        this.add_exactly_one_clause(&vars)?;
+                        vars
+                    }
+                    Encoding::Order => {
+                        let vars: Vec<i32> = (1..count).map(|_| this.alloc_var()).try_collect()?;
+                        for (a, b) in vars.iter().tuple_windows() {
+                            this.add_clause(vec![-b, *a]);
+                        }
+                        vars
+                    }
+                    Encoding::Log => {
+                        let bit_count = usize::BITS - (count - 1).leading_zeros();
+                        let vars: Vec<i32> =
+                            (0..bit_count).map(|_| this.alloc_var()).try_collect()?;
+                        // Exclude offsets beyond the largest value
+                        for offset in count..1 << bit_count {
+                            this.add_clause(bit_literals(&vars, offset).map(|l| -l).collect());
+                        }
+                        vars
+                    }
+                };
+                this.grid_vars[x].push(Some(vars));
             }
         }
         Ok(this)
     }
 
-    pub fn get_cell_vars(&self, cell: impl Borrow<Cell>) -> &[i32] {
+    pub fn get_cell_vars(&mut self, cell: impl Borrow<Cell>) -> Result<Vec<i32>, GridCspError> {
         let cell = cell.borrow();
-        self.grid_vars[cell.x][cell.y].as_ref()
+        if self.grid_vars[cell.x][cell.y].is_none() {
+            return Ok(Vec::new());
+        }
+        (self.number_min..=self.number_max)
+            .map(|value| self.get_value_var(cell, value))
+            .collect()
+    }
+
+    // The cell holds the value iff all these literals of its encoding are true
+    fn value_literals(&self, cell: Cell, value: u64) -> Vec<i32> {
+        let offset = (value - self.number_min) as usize;
+        let vars = self.grid_vars[cell.x][cell.y].as_ref().unwrap();
+        match self.options.encoding {
+            Encoding::OneHot => vec![vars[offset]],
+            Encoding::Order => [
+                offset.checked_sub(1).map(|i| vars[i]),
+                vars.get(offset).map(|v| -v),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            Encoding::Log => bit_literals(vars, offset).collect(),
+        }
+    }
+
+    pub fn get_value_var(
+        &mut self,
+        cell: impl Borrow<Cell>,
+        value: u64,
+    ) -> Result<i32, GridCspError> {
+        let cell = *cell.borrow();
+        if let Some(var) = self.value_vars.get(&(cell, value)) {
+            return Ok(*var);
+        }
+        let literals = self.value_literals(cell, value);
+        if let [literal] = literals[..] {
+            return Ok(literal);
+        }
+        // Definitions are cached, so they must outlive the current scope
+        let var = self.alloc_var()?;
+        for literal in literals.iter() {
+            self.add_unscoped_clause(vec![-var, *literal]);
+        }
+        self.add_unscoped_clause(
+            [var]
+                .into_iter()
+                .chain(literals.iter().map(|l| -l))
+                .collect(),
+        );
+        self.value_vars.insert((cell, value), var);
+        Ok(var)
+    }
+
+    // Literal true whenever the cell holds the value, which is all that bounding value counts from
+    // above needs. A value var is only defined in full when it already exists
+    fn get_value_indicator(&mut self, cell: Cell, value: u64) -> Result<i32, GridCspError> {
+        if let Some(var) = self.value_vars.get(&(cell, value)) {
+            return Ok(*var);
+        }
+        let literals = self.value_literals(cell, value);
+        if let [literal] = literals[..] {
+            return Ok(literal);
+        }
+        let var = self.alloc_var()?;
+        self.add_forbidden_values_clause([var], [(cell, value)])?;
+        Ok(var)
+    }
+
+    // Only for values above the smallest one, as the cell is always at least that
+    pub fn get_at_least_var(
+        &mut self,
+        cell: impl Borrow<Cell>,
+        value: u64,
+    ) -> Result<i32, GridCspError> {
+        let cell = *cell.borrow();
+        if self.options.encoding == Encoding::Order {
+            let vars = self.grid_vars[cell.x][cell.y].as_ref().unwrap();
+            return Ok(vars[(value - self.number_min - 1) as usize]);
+        }
+        if let Some(var) = self.at_least_vars.get(&(cell, value)) {
+            return Ok(*var);
+        }
+        let values: Vec<i32> = (value..=self.number_max)
+            .map(|v| self.get_value_var(cell, v))
+            .try_collect()?;
+        let var = self.alloc_var()?;
+        for v in values.iter() {
+            self.add_unscoped_clause(vec![-v, var]);
+        }
+        self.add_unscoped_clause([-var].into_iter().chain(values).collect());
+        self.at_least_vars.insert((cell, value), var);
+        Ok(var)
+    }
+
+    // Bits of the value offset from the smallest value, least significant first
+    fn get_offset_bits(&mut self, cell: Cell) -> Result<Vec<i32>, GridCspError> {
+        if self.options.encoding == Encoding::Log {
+            return Ok(self.grid_vars[cell.x][cell.y].clone().unwrap());
+        }
+        if let Some(bits) = self.offset_bits.get(&cell) {
+            return Ok(bits.clone());
+        }
+        let range = (self.number_max - self.number_min) as usize;
+        let bits: Vec<i32> = (0..usize::BITS - range.leading_zeros())
+            .map(|_| self.alloc_var())
+            .try_collect()?;
+        // Definitions are cached, so they must outlive the current scope
+        for offset in 0..=range {
+            let literals = self.value_literals(cell, self.number_min + offset as u64);
+            for (b, bit) in bits.iter().enumerate() {
+                let bit = if offset >> b & 1 == 1 { *bit } else { -bit };
+                self.add_unscoped_clause(literals.iter().map(|l| -l).chain([bit]).collect());
+            }
+        }
+        self.offset_bits.insert(cell, bits.clone());
+        Ok(bits)
     }
 
     pub fn alloc_var(&mut self) -> Result<i32, GridCspError> {
@@ -47,19 +348,78 @@ impl GridCspSolver {
         Ok(self.var_count)
     }
 
-    pub fn add_clause(&mut self, clause: Vec<i32>) {
+    pub fn add_clause(&mut self, mut clause: Vec<i32>) {
+        if let Some((activation, _, _)) = self.scopes.last() {
+            clause.push(-activation);
+        }
+        clause.extend(self.relaxation);
+        self.add_unscoped_clause(clause);
+    }
+
+    fn add_unscoped_clause(&mut self, clause: Vec<i32>) {
         debug_assert!(!clause.is_empty());
         debug_assert!(clause.iter().all(|v| *v != 0));
         debug_assert!(clause.iter().all(|v| v.abs() <= self.var_count));
         self.clauses.push(clause)
     }
 
+    // splr rejects empty clauses, so a var is forced both ways instead, which still honours
+    // the current scope and relaxation
+    fn add_empty_clause(&mut self) -> Result<(), GridCspError> {
+        let unsat = self.alloc_var()?;
+        self.add_clause(vec![unsat]);
+        self.add_clause(vec![-unsat]);
+        Ok(())
+    }
+
+    // Forbids the cells from holding all these values at once, on the literals of their encoding
+    // so that no value var is needed. Value vars already defined are still shorter
+    fn add_forbidden_values_clause(
+        &mut self,
+        literals: impl IntoIterator<Item = i32>,
+        values: impl IntoIterator<Item = (Cell, u64)>,
+    ) -> Result<(), GridCspError> {
+        let clause: Vec<i32> = literals
+            .into_iter()
+            .chain(
+                values
+                    .into_iter()
+                    .flat_map(|(cell, value)| match self.value_vars.get(&(cell, value)) {
+                        Some(var) => vec![*var],
+                        None => self.value_literals(cell, value),
+                    })
+                    .map(|l| -l),
+            )
+            .collect();
+        if clause.is_empty() {
+            return self.add_empty_clause();
+        }
+        self.add_clause(clause);
+        Ok(())
+    }
+
     pub fn add_alo_clause(&mut self, vars: impl AsRef<[i32]>) {
         self.add_clause(vars.as_ref().to_vec());
     }
 
-    pub fn add_amo_clause(&mut self, vars: impl AsRef<[i32]>) {
+    pub fn add_amo_clause(&mut self, vars: impl AsRef<[i32]>) -> Result<(), GridCspError> {
         let vars = vars.as_ref();
+        match self.options.amo_encoding {
+            _ if self.prefers_pairwise_amo(vars.len()) => self.add_pairwise_amo_clause(vars),
+            AmoEncoding::Auto | AmoEncoding::Sequential => self.add_sequential_amo_clause(vars)?,
+            AmoEncoding::Bimander => self.add_bimander_amo_clause(vars)?,
+            AmoEncoding::Commander => self.add_commander_amo_clause(vars)?,
+            AmoEncoding::Pairwise => self.add_pairwise_amo_clause(vars),
+            AmoEncoding::Product => self.add_product_amo_clause(vars)?,
+        }
+        Ok(())
+    }
+
+    fn prefers_pairwise_amo(&self, count: usize) -> bool {
+        count <= 4 || (self.options.amo_encoding == AmoEncoding::Auto && count <= 10)
+    }
+
+    fn add_pairwise_amo_clause(&mut self, vars: &[i32]) {
         for i in 0..vars.len() {
             for j in i + 1..vars.len() {
                 self.add_clause(vec![-vars[i], -vars[j]]);
@@ -67,6 +427,99 @@ impl GridCspSolver {
         }
     }
 
+    fn add_sequential_amo_clause(&mut self, vars: &[i32]) -> Result<(), GridCspError> {
+        // Sequential counter from Sinz paper, counter i is true iff one of the first i + 1 vars is
+        let mut previous: Option<i32> = None;
+        for (i, var) in vars.iter().enumerate() {
+            if let Some(previous) = previous {
+                self.add_clause(vec![-var, -previous]);
+            }
+            if i + 1 == vars.len() {
+                break;
+            }
+            let counter = self.alloc_var()?;
+            self.add_clause(vec![-var, counter]);
+            if let Some(previous) = previous {
+                self.add_clause(vec![-previous, counter]);
+            }
+            previous = Some(counter);
+        }
+        Ok(())
+    }
+
+    fn add_commander_amo_clause(&mut self, vars: &[i32]) -> Result<(), GridCspError> {
+        // Commander encoding from Kleiner and Kwon paper, without the at least one part
+        let mut commanders = Vec::<i32>::with_capacity(vars.len() / 3 + 1);
+        for group in vars.chunks(3) {
+            self.add_pairwise_amo_clause(group);
+            if group.len() > 1 {
+                let commander = self.alloc_var()?;
+                group
+                    .iter()
+                    .for_each(|var| self.add_clause(vec![-var, commander]));
+                commanders.push(commander);
+            } else {
+                commanders.push(group[0]);
+            }
+        }
+        self.add_amo_clause(commanders)
+    }
+
+    fn add_product_amo_clause(&mut self, vars: &[i32]) -> Result<(), GridCspError> {
+        // Product encoding from Chen paper, vars are laid out on a grid with one var per row and
+        // per column
+        let mut columns = vars.len().isqrt();
+        if columns * columns < vars.len() {
+            columns += 1;
+        }
+        let rows = vars.len().div_ceil(columns);
+        let row_vars: Vec<i32> = (0..rows).map(|_| self.alloc_var()).try_collect()?;
+        let column_vars: Vec<i32> = (0..columns).map(|_| self.alloc_var()).try_collect()?;
+        for (i, var) in vars.iter().enumerate() {
+            self.add_clause(vec![-var, row_vars[i / columns]]);
+            self.add_clause(vec![-var, column_vars[i % columns]]);
+        }
+        self.add_amo_clause(row_vars)?;
+        self.add_amo_clause(column_vars)
+    }
+
+    fn add_bimander_amo_clause(&mut self, vars: &[i32]) -> Result<(), GridCspError> {
+        // Bimander encoding from Nguyen and Mai paper, with groups of two vars whose index is
+        // binary encoded
+        let groups = vars.len().div_ceil(2);
+        let bit_count = usize::BITS - (groups - 1).leading_zeros();
+        let bits: Vec<i32> = (0..bit_count).map(|_| self.alloc_var()).try_collect()?;
+        for (g, group) in vars.chunks(2).enumerate() {
+            self.add_pairwise_amo_clause(group);
+            for var in group {
+                for (b, bit) in bits.iter().enumerate() {
+                    self.add_clause(vec![-var, if g >> b & 1 == 1 { *bit } else { -bit }]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_unique_clause(&mut self, cells: impl AsRef<[Cell]>) -> Result<(), GridCspError> {
+        let cells = cells.as_ref();
+        // Cells of the other encodings forbid equal pairs wherever values would be counted
+        // pairwise, which takes fewer clauses
+        if self.options.encoding != Encoding::OneHot && self.prefers_pairwise_amo(cells.len()) {
+            for (a, b) in cells.iter().tuple_combinations() {
+                self.add_forbidden_pair_clause(*a, *b, |a, b| a == b)?;
+            }
+            return Ok(());
+        }
+        for value in self.number_min..=self.number_max {
+            let vars: Vec<i32> = cells
+                .iter()
+                .map(|c| self.get_value_indicator(*c, value))
+                .try_collect()?;
+            self.add_amo_clause(vars)?;
+        }
+        Ok(())
+    }
+
     pub fn add_exactly_one_clause(&mut self, vars: impl AsRef<[i32]>) -> Result<(), GridCspError> {
         // Adapted commander encoding from Kleiner and Kwon paper
         let mut current = vars.as_ref().to_vec();
@@ -91,135 +544,1456 @@ impl GridCspSolver {
             next.clear();
         }
         self.add_alo_clause(&current);
-        self.add_amo_clause(&current);
+        self.add_pairwise_amo_clause(&current);
+        Ok(())
+    }
+
+    pub fn add_cardinality_clause(
+        &mut self,
+        vars: impl AsRef<[i32]>,
+        min: usize,
+        max: usize,
+    ) -> Result<(), GridCspError> {
+        let vars = vars.as_ref();
+        if min > vars.len() || min > max {
+            self.add_empty_clause()?;
+            return Ok(());
+        }
+        if max == 0 {
+            vars.iter().for_each(|v| self.add_clause(vec![-v]));
+            return Ok(());
+        } else if min == vars.len() {
+            vars.iter().for_each(|v| self.add_clause(vec![*v]));
+            return Ok(());
+        } else if max == 1 {
+            // Same encoding as unique groups
+            self.add_amo_clause(vars)?;
+            if min == 1 {
+                self.add_alo_clause(vars);
+            }
+            return Ok(());
+        }
+        // Sequential counter from Sinz paper, counters[j] is true iff at least j + 1 vars are true
+        let bound = min.max(max.saturating_add(1)).min(vars.len());
+        let mut counters = Vec::<i32>::with_capacity(bound);
+        for (i, var) in vars.iter().enumerate() {
+            let mut next = Vec::<i32>::with_capacity(bound);
+            for j in 0..bound.min(i + 1) {
+                let c = self.alloc_var()?;
+                let carry = if j == 0 { None } else { Some(counters[j - 1]) };
+                let keep = counters.get(j).copied();
+                if let Some(k) = keep {
+                    self.add_clause(vec![-k, c]);
+                }
+                match carry {
+                    Some(carry) => {
+                        self.add_clause(vec![-carry, -var, c]);
+                        self.add_clause([vec![-c, carry], keep.into_iter().collect()].concat());
+                    }
+                    None => self.add_clause(vec![-var, c]),
+                }
+                self.add_clause([vec![-c, *var], keep.into_iter().collect()].concat());
+                next.push(c);
+            }
+            counters = next;
+        }
+        if min > 0 {
+            self.add_clause(vec![counters[min - 1]]);
+        }
+        if max < counters.len() {
+            self.add_clause(vec![-counters[max]]);
+        }
+        Ok(())
+    }
+
+    pub fn add_visible_clause(
+        &mut self,
+        cells: impl AsRef<[Cell]>,
+        count: u64,
+    ) -> Result<(), GridCspError> {
+        let cells = cells.as_ref();
+        let values: Vec<u64> = (self.number_min..=self.number_max).collect();
+        // taller[v] is true iff one of the previous cells holds a value greater than values[v]
+        let mut taller: Vec<Option<i32>> = vec![None; values.len()];
+        let mut visible = Vec::<i32>::with_capacity(cells.len());
+        for (i, cell) in cells.iter().enumerate() {
+            let vis = self.alloc_var()?;
+            for (value, t) in values.iter().zip(taller.clone()) {
+                match t {
+                    Some(t) => {
+                        self.add_forbidden_values_clause([t, vis], [(*cell, *value)])?;
+                        self.add_forbidden_values_clause([-t, -vis], [(*cell, *value)])?;
+                    }
+                    None => self.add_forbidden_values_clause([vis], [(*cell, *value)])?,
+                }
+            }
+            visible.push(vis);
+            if i + 1 == cells.len() {
+                break;
+            }
+            let mut next = Vec::with_capacity(values.len());
+            for (v, previous) in taller.iter().enumerate() {
+                if v + 1 == values.len() {
+                    next.push(None);
+                    continue;
+                }
+                // Either this cell is greater, or it is not and a previous one was
+                let t = self.alloc_var()?;
+                for (w, value) in values.iter().enumerate() {
+                    if w > v {
+                        self.add_forbidden_values_clause([t], [(*cell, *value)])?;
+                    } else {
+                        let literals = [-t].into_iter().chain(*previous);
+                        self.add_forbidden_values_clause(literals, [(*cell, *value)])?;
+                    }
+                }
+                if let Some(previous) = previous {
+                    self.add_clause(vec![-previous, t]);
+                }
+                next.push(Some(t));
+            }
+            taller = next;
+        }
+        self.add_cardinality_clause(visible, count as usize, count as usize)
+    }
+
+    pub fn add_sandwich_clause(
+        &mut self,
+        cells: impl AsRef<[Cell]>,
+        low: u64,
+        high: u64,
+        sum: u64,
+    ) -> Result<(), GridCspError> {
+        let cells = cells.as_ref();
+        // Every filling is enumerated before any clause is added, as one may be too large
+        let mut fillings = Vec::new();
+        for i in 0..cells.len() {
+            for j in i + 1..cells.len() {
+                let between = &cells[i + 1..j];
+                if between.is_empty() && sum == 0 {
+                    continue;
+                }
+                // The crust is the closest low and high pair, so the filling never contains them
+                let solutions = (!between.is_empty())
+                    .then(|| add_enumerator(sum, between.len(), self.number_max))
+                    .into_iter()
+                    .flatten()
+                    .filter(|s| !s.contains(&low) && !s.contains(&high));
+                fillings.push((i, j, self.enumerate_alternatives(solutions)?));
+            }
+        }
+        for (i, j, solutions) in fillings {
+            let crust = self.alloc_var()?;
+            for (first, last) in [(low, high), (high, low)] {
+                self.add_forbidden_values_clause([crust], [(cells[i], first), (cells[j], last)])?;
+            }
+            self.add_conditional_alternative_clause([crust], &cells[i + 1..j], solutions)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_arrow_clause(
+        &mut self,
+        circle: Cell,
+        cells: impl AsRef<[Cell]>,
+    ) -> Result<(), GridCspError> {
+        let cells = cells.as_ref();
+        let sums: Vec<Vec<Vec<u64>>> = (self.number_min..=self.number_max)
+            .map(|value| {
+                self.enumerate_alternatives(add_enumerator(value, cells.len(), self.number_max))
+            })
+            .try_collect()?;
+        for (value, solutions) in (self.number_min..=self.number_max).zip(sums) {
+            let conditions = self.value_literals(circle, value);
+            self.add_conditional_alternative_clause(conditions, cells, solutions)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_pair_clause(
+        &mut self,
+        a: Cell,
+        b: Cell,
+        allowed: impl Fn(u64, u64) -> bool,
+    ) -> Result<(), GridCspError> {
+        // Supports are value vars, which only one-hot cells have natively
+        if self.options.encoding != Encoding::OneHot {
+            return self.add_forbidden_pair_clause(a, b, |a, b| !allowed(a, b));
+        }
+        let a_vars = self.get_cell_vars(a)?;
+        let b_vars = self.get_cell_vars(b)?;
+        for (i, a_var) in a_vars.iter().enumerate() {
+            let supports: Vec<i32> = b_vars
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| allowed(self.number_min + i as u64, self.number_min + *j as u64))
+                .map(|(_, b_var)| *b_var)
+                .collect();
+            self.add_clause([vec![-a_var], supports].concat());
+        }
+        for (j, b_var) in b_vars.iter().enumerate() {
+            let supports: Vec<i32> = a_vars
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| allowed(self.number_min + *i as u64, self.number_min + j as u64))
+                .map(|(_, a_var)| *a_var)
+                .collect();
+            self.add_clause([vec![-b_var], supports].concat());
+        }
+        Ok(())
+    }
+
+    pub fn add_forbidden_pair_clause(
+        &mut self,
+        a: Cell,
+        b: Cell,
+        forbidden: impl Fn(u64, u64) -> bool,
+    ) -> Result<(), GridCspError> {
+        let values = self.number_min..=self.number_max;
+        for (i, j) in values.clone().cartesian_product(values) {
+            if forbidden(i, j) {
+                self.add_forbidden_values_clause([], [(a, i), (b, j)])?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_line_clause(
+        &mut self,
+        cells: impl AsRef<[Cell]>,
+        line: LineConstraint,
+    ) -> Result<(), GridCspError> {
+        let cells = cells.as_ref();
+        match line {
+            LineConstraint::Between => {
+                let (first, last) = (cells[0], cells[cells.len() - 1]);
+                let values = self.number_min..=self.number_max;
+                for cell in cells[1..cells.len() - 1].iter() {
+                    for (i, j) in values.clone().cartesian_product(values.clone()) {
+                        for v in values.clone().filter(|v| *v <= i.min(j) || *v >= i.max(j)) {
+                            self.add_forbidden_values_clause(
+                                [],
+                                [(first, i), (last, j), (*cell, v)],
+                            )?;
+                        }
+                    }
+                }
+            }
+            LineConstraint::Palindrome => {
+                for i in 0..cells.len() / 2 {
+                    self.add_pair_clause(cells[i], cells[cells.len() - 1 - i], |a, b| a == b)?;
+                }
+            }
+            LineConstraint::Renban => {
+                self.add_unique_clause(cells)?;
+                let length = cells.len() as u64;
+                for (a, b) in cells.iter().tuple_combinations() {
+                    self.add_pair_clause(*a, *b, |a, b| a.abs_diff(b) < length)?;
+                }
+            }
+            LineConstraint::Whisper(difference) => {
+                for (a, b) in cells.iter().tuple_windows() {
+                    self.add_pair_clause(*a, *b, |a, b| a.abs_diff(b) >= difference)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_successor_clause(
+        &mut self,
+        cell: Cell,
+        neighbours: impl AsRef<[Cell]>,
+    ) -> Result<(), GridCspError> {
+        let vars = self.get_cell_vars(cell)?;
+        let neighbour_vars: Vec<Vec<i32>> = neighbours
+            .as_ref()
+            .iter()
+            .map(|n| self.get_cell_vars(n))
+            .try_collect()?;
+        for v in 0..vars.len() {
+            // The successor is needed for propagation, the predecessor only speeds it up
+            if v + 1 < vars.len() {
+                let successors = neighbour_vars.iter().map(|vs| vs[v + 1]);
+                self.add_clause([-vars[v]].into_iter().chain(successors).collect());
+            }
+            if v > 0 {
+                let predecessors = neighbour_vars.iter().map(|vs| vs[v - 1]);
+                self.add_clause([-vars[v]].into_iter().chain(predecessors).collect());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_connected_clause(
+        &mut self,
+        cells: impl AsRef<[Cell]>,
+        value: u64,
+    ) -> Result<(), GridCspError> {
+        // Every cell holding the value must be reached from a root within the cell count steps,
+        // the root being the first of these cells
+        let cells = cells.as_ref();
+        let index: HashMap<Cell, usize> = cells.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+        let neighbours: Vec<Vec<usize>> = cells
+            .iter()
+            .map(|c| {
+                ORTHOGONAL_MOVES
+                    .iter()
+                    .flat_map(|(dx, dy)| [(*dx, *dy), (-dx, -dy)])
+                    .filter_map(|(dx, dy)| {
+                        let n = Cell::new(c.x.checked_add_signed(dx)?, c.y.checked_add_signed(dy)?);
+                        index.get(&n).copied()
+                    })
+                    .collect()
+            })
+            .collect();
+        let vars: Vec<i32> = cells
+            .iter()
+            .map(|c| self.get_value_var(c, value))
+            .try_collect()?;
+
+        let mut reached = Vec::<i32>::with_capacity(vars.len());
+        let mut previous: Option<i32> = None;
+        for var in vars.iter() {
+            let root = self.alloc_var()?;
+            self.add_clause(vec![-root, *var]);
+            let prefix = self.alloc_var()?;
+            self.add_clause(vec![-var, prefix]);
+            if let Some(previous) = previous {
+                self.add_clause(vec![-root, -previous]);
+                self.add_clause(vec![-previous, prefix]);
+            }
+            previous = Some(prefix);
+            reached.push(root);
+        }
+        for _ in 1..vars.len() {
+            let mut next = Vec::<i32>::with_capacity(vars.len());
+            for (i, var) in vars.iter().enumerate() {
+                let step = self.alloc_var()?;
+                self.add_clause(vec![-step, *var]);
+                self.add_clause(
+                    [-step, reached[i]]
+                        .into_iter()
+                        .chain(neighbours[i].iter().map(|n| reached[*n]))
+                        .collect(),
+                );
+                next.push(step);
+            }
+            reached = next;
+        }
+        for (var, reached) in vars.iter().zip(reached) {
+            self.add_clause(vec![-var, reached]);
+        }
+        Ok(())
+    }
+
+    pub fn add_max_run_clause(
+        &mut self,
+        cells: impl AsRef<[Cell]>,
+        length: usize,
+    ) -> Result<(), GridCspError> {
+        // Inactive cells break runs
+        for window in cells.as_ref().windows(length.saturating_add(1)) {
+            if window.iter().any(|c| !self.problem.is_active(*c)) {
+                continue;
+            }
+            for value in self.number_min..=self.number_max {
+                self.add_forbidden_values_clause([], window.iter().map(|c| (*c, value)))?;
+            }
+        }
         Ok(())
     }
 
+    pub fn add_runs_clause(
+        &mut self,
+        cells: impl AsRef<[Cell]>,
+        runs: impl AsRef<[usize]>,
+    ) -> Result<(), GridCspError> {
+        // Unrolled automaton over the pattern of shaded (true) and separating (false) cells,
+        // state j having matched the first j symbols of the pattern
+        // Inactive cells are never shaded
+        let shaded: Vec<Option<i32>> = cells
+            .as_ref()
+            .iter()
+            .map(|c| {
+                self.problem
+                    .is_active(*c)
+                    .then(|| self.get_value_var(c, 1))
+                    .transpose()
+            })
+            .try_collect()?;
+        let mut pattern = Vec::<bool>::new();
+        for run in runs.as_ref().iter().filter(|r| **r > 0) {
+            if !pattern.is_empty() {
+                pattern.push(false);
+            }
+            pattern.extend(std::iter::repeat_n(true, *run));
+        }
+        let (n, m) = (shaded.len(), pattern.len());
+        if m > n {
+            self.add_empty_clause()?;
+            return Ok(());
+        }
+        let next = |j: usize, value: bool| -> Option<usize> {
+            if j < m && pattern[j] == value {
+                Some(j + 1)
+            } else if !value && (j == 0 || j == m || !pattern[j - 1]) {
+                Some(j)
+            } else {
+                None
+            }
+        };
+
+        // Only states that are reachable and can still be accepted get a var
+        let mut states: Vec<Vec<Option<i32>>> = Vec::with_capacity(n + 1);
+        for t in 0..=n {
+            let mut row = Vec::with_capacity(m + 1);
+            for j in 0..=m {
+                row.push(if j <= t && m - j <= n - t {
+                    Some(self.alloc_var()?)
+                } else {
+                    None
+                });
+            }
+            states.push(row);
+        }
+        self.add_alo_clause([states[0][0].unwrap()]);
+        for t in 0..n {
+            let mut predecessors = vec![Vec::<i32>::new(); m + 1];
+            for j in 0..=m {
+                let Some(state) = states[t][j] else {
+                    continue;
+                };
+                for value in [true, false] {
+                    let input = match (shaded[t], value) {
+                        (None, true) => continue,
+                        (None, false) => None,
+                        (Some(var), true) => Some(-var),
+                        (Some(var), false) => Some(var),
+                    };
+                    let clause = [-state].into_iter().chain(input);
+                    match next(j, value).and_then(|k| states[t + 1][k].map(|s| (k, s))) {
+                        Some((k, successor)) => {
+                            self.add_clause(clause.chain([successor]).collect());
+                            predecessors[k].push(state);
+                        }
+                        None => self.add_clause(clause.collect()),
+                    }
+                }
+            }
+            for (k, state) in states[t + 1].iter().enumerate() {
+                if let Some(state) = state {
+                    self.add_clause(
+                        [-state]
+                            .into_iter()
+                            .chain(predecessors[k].clone())
+                            .collect(),
+                    );
+                }
+            }
+            let alive: Vec<i32> = states[t + 1].iter().flatten().copied().collect();
+            self.add_amo_clause(alive)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_distinct_clause(
+        &mut self,
+        a: impl AsRef<[Cell]>,
+        b: impl AsRef<[Cell]>,
+    ) -> Result<(), GridCspError> {
+        let mut differences = Vec::new();
+        for (a, b) in a.as_ref().iter().zip(b.as_ref().iter()) {
+            if !self.problem.is_active(*a) || !self.problem.is_active(*b) {
+                continue;
+            }
+            let d = self.alloc_var()?;
+            for value in self.number_min..=self.number_max {
+                self.add_forbidden_values_clause([-d], [(*a, value), (*b, value)])?;
+            }
+            differences.push(d);
+        }
+        // Lines without any pair of active cells to compare can never differ
+        if differences.is_empty() {
+            self.add_empty_clause()?;
+            return Ok(());
+        }
+        self.add_clause(differences);
+        Ok(())
+    }
+
+    pub fn add_sum_clause(
+        &mut self,
+        cells: impl AsRef<[Cell]>,
+        sum: u64,
+    ) -> Result<(), GridCspError> {
+        let cells = cells.as_ref();
+        let target = (cells.len() as u64)
+            .checked_mul(self.number_min)
+            .and_then(|s| sum.checked_sub(s))
+            .and_then(|t| usize::try_from(t).ok());
+        let range = (self.number_max - self.number_min) as usize;
+        let Some(target) = target.filter(|t| *t <= cells.len() * range) else {
+            self.add_empty_clause()?;
+            return Ok(());
+        };
+        // Partial sums above the target are all alike
+        let cap = target + 1;
+        // The size is estimated before any clause is added
+        let oversized = totalizer_size(cells.len(), range, cap) > self.options.totalizer_limit;
+        match self.options.sum_encoding {
+            SumEncoding::Adder => return self.add_adder_sum_clause(cells, target),
+            SumEncoding::Totalizer if oversized => return Err(GridCspError::ConstraintTooLarge),
+            SumEncoding::Auto | SumEncoding::Enumeration if oversized => {
+                return self.add_adder_sum_clause(cells, target);
+            }
+            _ => {}
+        }
+        // Totalizer from Bailleux and Boufkhad paper over the value offsets, where each node
+        // holds the unary representation of its partial sum, node[k] being true iff the partial
+        // sum is at least k + 1
+        let nodes: Vec<Vec<i32>> = cells
+            .iter()
+            .map(|c| {
+                (1..=range.min(cap))
+                    .map(|k| self.get_at_least_var(c, self.number_min + k as u64))
+                    .collect()
+            })
+            .try_collect()?;
+        let root = self.add_totalizer_tree(nodes, cap)?;
+        if target > 0 {
+            self.add_clause(vec![root[target - 1]]);
+        }
+        if let Some(over) = root.get(target) {
+            self.add_clause(vec![-over]);
+        }
+        Ok(())
+    }
+
+    // The offsets are summed in binary, so that the size only grows with their bit count
+    fn add_adder_sum_clause(&mut self, cells: &[Cell], target: usize) -> Result<(), GridCspError> {
+        let numbers: Vec<Vec<i32>> = cells
+            .iter()
+            .map(|c| self.get_offset_bits(*c))
+            .try_collect()?;
+        let root = self.add_adder_tree(numbers)?;
+        if target.checked_shr(root.len() as u32).unwrap_or(0) > 0 {
+            return self.add_empty_clause();
+        }
+        for (b, bit) in root.iter().enumerate() {
+            self.add_clause(vec![if target >> b & 1 == 1 { *bit } else { -bit }]);
+        }
+        Ok(())
+    }
+
+    // Adds the binary numbers pairwise up to their sum, bits being least significant first
+    fn add_adder_tree(&mut self, mut numbers: Vec<Vec<i32>>) -> Result<Vec<i32>, GridCspError> {
+        while numbers.len() > 1 {
+            let mut merged = Vec::with_capacity(numbers.len().div_ceil(2));
+            for pair in numbers.chunks(2) {
+                match pair {
+                    [a, b] => merged.push(self.add_adder_node(a, b)?),
+                    _ => merged.push(pair[0].clone()),
+                }
+            }
+            numbers = merged;
+        }
+        Ok(numbers.pop().unwrap_or_default())
+    }
+
+    // Ripple carry adder
+    fn add_adder_node(&mut self, a: &[i32], b: &[i32]) -> Result<Vec<i32>, GridCspError> {
+        let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+        let mut carry = None;
+        for i in 0..a.len().max(b.len()) {
+            let inputs: Vec<i32> = [a.get(i), b.get(i), carry.as_ref()]
+                .into_iter()
+                .flatten()
+                .copied()
+                .collect();
+            if let [input] = inputs[..] {
+                sum.push(input);
+                carry = None;
+                continue;
+            }
+            let (bit, next) = (self.alloc_var()?, self.alloc_var()?);
+            // The bit is the parity of the inputs, each assignment of them forcing it
+            for mask in 0..1u32 << inputs.len() {
+                let mut clause: Vec<i32> = inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| if mask >> i & 1 == 1 { -x } else { *x })
+                    .collect();
+                clause.push(if mask.count_ones() % 2 == 1 {
+                    bit
+                } else {
+                    -bit
+                });
+                self.add_clause(clause);
+            }
+            // The carry is set iff at least two inputs are
+            for (x, y) in inputs.iter().tuple_combinations() {
+                self.add_clause(vec![-x, -y, next]);
+            }
+            for others in inputs.iter().combinations(inputs.len() - 1) {
+                self.add_clause(
+                    [-next]
+                        .into_iter()
+                        .chain(others.into_iter().copied())
+                        .collect(),
+                );
+            }
+            sum.push(bit);
+            carry = Some(next);
+        }
+        sum.extend(carry);
+        Ok(sum)
+    }
+
+    // Merges the unary leaves pairwise up to the root of the totalizer
+    fn add_totalizer_tree(
+        &mut self,
+        mut nodes: Vec<Vec<i32>>,
+        cap: usize,
+    ) -> Result<Vec<i32>, GridCspError> {
+        while nodes.len() > 1 {
+            let mut merged = Vec::with_capacity(nodes.len().div_ceil(2));
+            for pair in nodes.chunks(2) {
+                match pair {
+                    [a, b] => merged.push(self.add_totalizer_node(a, b, cap)?),
+                    _ => merged.push(pair[0].clone()),
+                }
+            }
+            nodes = merged;
+        }
+        Ok(nodes.pop().unwrap_or_default())
+    }
+
+    fn add_totalizer_node(
+        &mut self,
+        a: &[i32],
+        b: &[i32],
+        cap: usize,
+    ) -> Result<Vec<i32>, GridCspError> {
+        let node: Vec<i32> = (0..(a.len() + b.len()).min(cap))
+            .map(|_| self.alloc_var())
+            .try_collect()?;
+        for i in 0..=a.len() {
+            for j in 0..=b.len() {
+                let k = i + j;
+                // At least i and at least j give at least i + j
+                if k > 0 {
+                    let mut clause = vec![node[k.min(node.len()) - 1]];
+                    clause.extend(i.checked_sub(1).map(|i| -a[i]));
+                    clause.extend(j.checked_sub(1).map(|j| -b[j]));
+                    self.add_clause(clause);
+                }
+                // At most i and at most j give at most i + j
+                if k < node.len() {
+                    let mut clause = vec![-node[k]];
+                    clause.extend(a.get(i));
+                    clause.extend(b.get(j));
+                    self.add_clause(clause);
+                }
+            }
+        }
+        Ok(node)
+    }
+
+    fn prefers_sum_clause(&self, alternatives: u64) -> bool {
+        match self.options.sum_encoding {
+            SumEncoding::Auto => alternatives > SUM_ENUMERATION_LIMIT,
+            SumEncoding::Enumeration => false,
+            SumEncoding::Totalizer | SumEncoding::Adder => true,
+        }
+    }
+
+    // Alternatives are gathered before any clause is added, so that a constraint with too many
+    // of them is rejected as a whole rather than partially encoded
+    fn enumerate_alternatives(
+        &self,
+        solutions: impl IntoIterator<Item = Vec<u64>>,
+    ) -> Result<Vec<Vec<u64>>, GridCspError> {
+        let limit = self.options.enumeration_limit;
+        let solutions: Vec<Vec<u64>> = solutions
+            .into_iter()
+            .take(limit.saturating_add(1))
+            .collect();
+        if solutions.len() > limit {
+            return Err(GridCspError::ConstraintTooLarge);
+        }
+        Ok(solutions)
+    }
+
     pub fn add_alternative_clause(
         &mut self,
         cells: impl AsRef<[Cell]>,
-        solutions: impl AsRef<[Vec<u64>]>,
+        solutions: impl IntoIterator<Item = Vec<u64>>,
+    ) -> Result<(), GridCspError> {
+        self.add_conditional_alternative_clause([], cells, solutions)
+    }
+
+    pub fn add_conditional_alternative_clause(
+        &mut self,
+        conditions: impl AsRef<[i32]>,
+        cells: impl AsRef<[Cell]>,
+        solutions: impl IntoIterator<Item = Vec<u64>>,
     ) -> Result<(), GridCspError> {
         let cells = cells.as_ref();
-        let solutions = solutions.as_ref();
+        let negated_conditions: Vec<i32> = conditions.as_ref().iter().map(|c| -c).collect();
         let mut z_vars = Vec::new();
-        for solution in solutions.iter() {
+        let mut supports = HashMap::<u64, Vec<i32>>::new();
+        for solution in self.enumerate_alternatives(solutions)? {
             let z = self.alloc_var()?;
             for (count, value) in solution.iter().dedup_with_count() {
                 let vars: Vec<i32> = cells
                     .iter()
-                    .map(|c| self.get_cell_vars(c)[*value as usize - 1])
-                    .collect();
+                    .map(|c| self.get_value_var(c, *value))
+                    .try_collect()?;
                 for indexes in (0..vars.len()).combinations(cells.len() + 1 - count) {
                     self.add_clause([vec![-z], indexes.iter().map(|i| vars[*i]).collect()].concat())
                 }
+                supports.entry(*value).or_default().push(z);
             }
             z_vars.push(z);
         }
-        self.add_clause(z_vars);
+        // One of the alternatives must hold whenever all the conditions hold
+        if negated_conditions.is_empty() && z_vars.is_empty() {
+            self.add_empty_clause()?;
+            return Ok(());
+        }
+        self.add_clause([negated_conditions.clone(), z_vars].concat());
+        // A cell value requires one of the alternatives using it
+        for cell in cells.iter() {
+            for value in self.number_min..=self.number_max {
+                let support = supports.get(&value).map_or(&[][..], |zs| zs.as_slice());
+                let literals = [&negated_conditions[..], support].concat();
+                self.add_forbidden_values_clause(literals, [(*cell, value)])?;
+            }
+        }
 
         Ok(())
     }
 
-    pub fn solve(&mut self) -> Result<Vec<Vec<u64>>, GridCspError> {
-        let mut solver = match Solver::try_from((Config::default(), self.clauses.as_ref())) {
-            Ok(solver) => solver,
-            Err(Ok(Certificate::UNSAT)) => return Err(GridCspError::NoSolution),
-            Err(Ok(Certificate::SAT(_))) => unreachable!(),
-            Err(Err(err)) => return Err(GridCspError::SolverError(err)),
+    // Gives the persistent solver the vars and clauses added since the last search
+    fn load_solver(&mut self) -> Result<&mut Solver, GridCspError> {
+        let mut solver = match self.solver.take() {
+            Some(solver) => solver,
+            None => match Solver::try_from((Config::default(), self.clauses.as_ref())) {
+                Ok(solver) => {
+                    self.loaded_clauses = self.clauses.len();
+                    self.dead_scopes = 0;
+                    solver
+                }
+                Err(Ok(Certificate::UNSAT)) => return Err(GridCspError::NoSolution),
+                Err(Ok(Certificate::SAT(_))) => unreachable!(),
+                Err(Err(err)) => return Err(solver_error(err)),
+            },
         };
-        let solution = match solver.solve()? {
-            Certificate::SAT(items) => items,
-            Certificate::UNSAT => return Err(GridCspError::NoSolution),
+        while solver.asg.num_vars < self.var_count as usize {
+            solver.add_var();
+        }
+        if self.loaded_clauses < self.clauses.len() {
+            for clause in self.clauses[self.loaded_clauses..].iter() {
+                solver.add_clause(clause).map_err(solver_error)?;
+            }
+            // Clears the trail saved by the previous search, which new clauses may invalidate
+            solver.reset();
+        }
+        self.loaded_clauses = self.clauses.len();
+        Ok(self.solver.insert(solver))
+    }
+
+    // Extra clauses only hold for this search and splr has no assumptions, so they are given
+    // to a copy of the persistent solver: it starts from what was learnt so far, but what it
+    // learns itself is lost
+    fn search(&mut self, extra_clauses: &[Vec<i32>]) -> Result<Vec<i32>, GridCspError> {
+        // Open scopes are switched on the same way, so that they can still be popped
+        let extra_clauses: Vec<Vec<i32>> = self
+            .scopes
+            .iter()
+            .map(|(activation, _, _)| vec![*activation])
+            .chain(extra_clauses.iter().cloned())
+            .collect();
+        let solver = self.load_solver()?;
+        let certificate = if extra_clauses.is_empty() {
+            solver.solve()
+        } else {
+            let mut solver = solver.clone();
+            extra_clauses
+                .iter()
+                .try_for_each(|clause| solver.add_clause(clause).map(|_| ()))
+                .and_then(|_| {
+                    solver.reset();
+                    solver.solve()
+                })
         };
-        let mut grid = Vec::<Vec<u64>>::with_capacity(self.grid_vars.len());
+        match certificate.map_err(solver_error)? {
+            Certificate::SAT(items) => Ok(items),
+            Certificate::UNSAT => Err(GridCspError::NoSolution),
+        }
+    }
+
+    pub fn solve(&mut self) -> Result<Vec<Vec<Option<u64>>>, GridCspError> {
+        self.solve_with_assumptions(&[])
+    }
+
+    pub fn solve_with_assumptions(
+        &mut self,
+        assumptions: &[(Cell, u64)],
+    ) -> Result<Vec<Vec<Option<u64>>>, GridCspError> {
+        let mut extra_clauses = Vec::with_capacity(assumptions.len());
+        for (cell, value) in assumptions.iter() {
+            match self
+                .grid_vars
+                .get(cell.x)
+                .and_then(|column| column.get(cell.y))
+            {
+                None => return Err(GridCspError::CellOutOfBound(cell.x, cell.y)),
+                Some(None) => return Err(GridCspError::InactiveCell(cell.x, cell.y)),
+                Some(Some(_)) => {}
+            }
+            if !(self.number_min..=self.number_max).contains(value) {
+                return Err(GridCspError::ValueOutOfBound(*value));
+            }
+            for literal in self.value_literals(*cell, *value) {
+                extra_clauses.push(vec![literal]);
+            }
+        }
+        let model = self.search(&extra_clauses)?;
+        self.decode(&model)
+    }
+
+    fn decode(&self, model: &[i32]) -> Result<Vec<Vec<Option<u64>>>, GridCspError> {
+        let mut grid = Vec::<Vec<Option<u64>>>::with_capacity(self.grid_vars.len());
         for column in self.grid_vars.iter() {
-            grid.push(Vec::<u64>::with_capacity(column.len()));
+            grid.push(Vec::<Option<u64>>::with_capacity(column.len()));
             for cell_vars in column.iter() {
-                let selected: Vec<u64> = cell_vars
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(i, v)| {
-                        if solution[*v as usize - 1] > 0 {
-                            Some(i as u64 + 1)
-                        } else {
-                            None
+                let Some(cell_vars) = cell_vars else {
+                    grid.last_mut().unwrap().push(None);
+                    continue;
+                };
+                let assigned = cell_vars.iter().map(|v| model[*v as usize - 1] > 0);
+                let offset = match self.options.encoding {
+                    Encoding::OneHot => {
+                        let selected: Vec<usize> = assigned.positions(|a| a).collect();
+                        if selected.len() != 1 {
+                            return Err(GridCspError::UnexpectedSolution);
                         }
-                    })
-                    .collect();
-                if selected.len() != 1 {
-                    return Err(GridCspError::UnexpectedSolution);
-                }
-                grid.last_mut().unwrap().push(selected[0]);
+                        selected[0]
+                    }
+                    Encoding::Order => assigned.filter(|a| *a).count(),
+                    Encoding::Log => assigned.enumerate().map(|(b, a)| usize::from(a) << b).sum(),
+                };
+                grid.last_mut()
+                    .unwrap()
+                    .push(Some(self.number_min + offset as u64));
             }
         }
         Ok(grid)
     }
 
-    #[allow(clippy::needless_range_loop)]
-    pub fn solve_unique(&mut self) -> Result<Vec<Vec<u64>>, GridCspError> {
+    pub fn solve_unique(&mut self) -> Result<Vec<Vec<Option<u64>>>, GridCspError> {
         let grid = self.solve()?;
 
         let mut antisolution = Vec::<i32>::new();
-        for x in 0..grid.len() {
-            for y in 0..grid[x].len() {
-                antisolution.push(-self.grid_vars[x][y][grid[x][y] as usize - 1]);
+        for (x, column) in grid.iter().enumerate() {
+            for (y, value) in column.iter().enumerate() {
+                if let Some(value) = value {
+                    let literals = self.value_literals(Cell::new(x, y), *value);
+                    antisolution.extend(literals.iter().map(|l| -l));
+                }
             }
         }
-        self.clauses.push(antisolution);
-        if self.solve().is_ok() {
+        if self.search(&[antisolution]).is_ok() {
             return Err(GridCspError::SolutionNotUnique);
         }
-        self.clauses.pop();
 
         Ok(grid)
     }
 }
 
-impl TryFrom<GenericProblem> for GridCspSolver {
-    type Error = GridCspError;
-
-    fn try_from(problem: GenericProblem) -> Result<Self, Self::Error> {
+impl GridCspSolver {
+    pub fn from_problem(
+        problem: GenericProblem,
+        options: SolverOptions,
+    ) -> Result<Self, GridCspError> {
         problem.validate()?;
-        let mut csp = GridCspSolver::new(problem.grid)?;
-        for cg in problem.constraints.iter() {
-            let cells = cg.group.to_cells(problem.grid);
-            match cg.constraint {
-                crate::model::Constraint::Add(v) => {
-                    let solutions = add_enumerator(v, cells.len(), problem.grid.number_max);
-                    csp.add_alternative_clause(cells, solutions)?;
-                }
-                crate::model::Constraint::Div(v) => {
-                    let solutions = div_enumerator(v, cells.len(), problem.grid.number_max);
-                    csp.add_alternative_clause(cells, solutions)?;
-                }
-                crate::model::Constraint::Equal(v) => {
-                    for cell in cells.iter() {
-                        let vars = csp.get_cell_vars(cell);
-                        csp.add_alo_clause([vars[v as usize - 1]]);
-                    }
-                }
-                crate::model::Constraint::Mul(v) => {
-                    let solutions = mul_enumerator(v, cells.len(), problem.grid.number_max);
-                    csp.add_alternative_clause(cells, solutions)?;
-                }
-                crate::model::Constraint::Sub(v) => {
-                    let solutions = sub_enumerator(v, cells.len(), problem.grid.number_max);
-                    csp.add_alternative_clause(cells, solutions)?;
-                }
-                crate::model::Constraint::Unique => {
-                    let vars: Vec<Vec<i32>> = cells
-                        .iter()
-                        .map(|c| csp.get_cell_vars(c).to_vec())
+        let mut csp =
+            GridCspSolver::with_options(problem.grid, problem.inactive.iter().copied(), options)?;
+        let distinct_groups = distinct_groups(&problem);
+        for (i, cg) in problem.constraints.iter().enumerate() {
+            csp.add_group_clauses(i, cg, problem.group_cells(&cg.group), &distinct_groups)?;
+        }
+        let paired: HashSet<(Cell, Cell)> = problem
+            .constraints
+            .iter()
+            .filter(|cg| cg.constraint.is_pairwise())
+            .map(|cg| problem.group_cells(&cg.group))
+            .flat_map(|cells| [(cells[0], cells[1]), (cells[1], cells[0])])
+            .collect();
+        for rule in problem.rules.iter() {
+            match rule {
+                GlobalRule::AntiKing => {
+                    for (a, b) in neighbour_pairs(&problem, &KING_MOVES) {
+                        csp.add_forbidden_pair_clause(a, b, |a, b| a == b)?;
+                    }
+                }
+                GlobalRule::AntiKnight => {
+                    for (a, b) in neighbour_pairs(&problem, &KNIGHT_MOVES) {
+                        csp.add_forbidden_pair_clause(a, b, |a, b| a == b)?;
+                    }
+                }
+                GlobalRule::Connected(value) => {
+                    let cells: Vec<Cell> = problem.active_cells().collect();
+                    csp.add_connected_clause(cells, *value)?;
+                }
+                GlobalRule::ConsecutivePath(adjacency) => {
+                    let moves: &[(isize, isize)] = match adjacency {
+                        Adjacency::King => &KING_MOVES,
+                        Adjacency::Orthogonal => &ORTHOGONAL_MOVES,
+                    };
+                    let mut neighbours = HashMap::<Cell, Vec<Cell>>::new();
+                    for (a, b) in neighbour_pairs(&problem, moves) {
+                        neighbours.entry(a).or_default().push(b);
+                        neighbours.entry(b).or_default().push(a);
+                    }
+                    for cell in problem.active_cells() {
+                        let cell_neighbours = neighbours.remove(&cell).unwrap_or_default();
+                        csp.add_successor_clause(cell, cell_neighbours)?;
+                    }
+                }
+                GlobalRule::DistinctColumns => {
+                    let columns: Vec<Vec<Cell>> = (0..problem.grid.width)
+                        .map(|x| problem.group_cells(&CellGroup::Column(x)))
+                        .collect();
+                    // Columns are compared on the rows active in both
+                    for (a, b) in columns.iter().tuple_combinations() {
+                        let (a, b): (Vec<Cell>, Vec<Cell>) = a
+                            .iter()
+                            .merge_join_by(b, |a, b| a.y.cmp(&b.y))
+                            .filter_map(|pair| pair.both())
+                            .unzip();
+                        csp.add_distinct_clause(a, b)?;
+                    }
+                }
+                GlobalRule::DistinctRows => {
+                    let rows: Vec<Vec<Cell>> = (0..problem.grid.height)
+                        .map(|y| problem.group_cells(&CellGroup::Row(y)))
                         .collect();
-                    for v in 0..problem.grid.number_max {
-                        csp.add_amo_clause(
-                            vars.iter().map(|vs| vs[v as usize]).collect::<Vec<i32>>(),
-                        );
+                    for (a, b) in rows.iter().tuple_combinations() {
+                        let (a, b): (Vec<Cell>, Vec<Cell>) = a
+                            .iter()
+                            .merge_join_by(b, |a, b| a.x.cmp(&b.x))
+                            .filter_map(|pair| pair.both())
+                            .unzip();
+                        csp.add_distinct_clause(a, b)?;
+                    }
+                }
+                GlobalRule::Negative(constraint) => {
+                    for (a, b) in neighbour_pairs(&problem, &ORTHOGONAL_MOVES) {
+                        if !paired.contains(&(a, b)) {
+                            csp.add_forbidden_pair_clause(a, b, |a, b| {
+                                constraint.pair_holds(a, b)
+                            })?;
+                        }
+                    }
+                }
+                GlobalRule::NoTouching(value) => {
+                    for (a, b) in neighbour_pairs(&problem, &KING_MOVES) {
+                        csp.add_forbidden_pair_clause(a, b, |a, b| a == *value && b == *value)?;
+                    }
+                }
+                GlobalRule::NonConsecutiveOrthogonal => {
+                    for (a, b) in neighbour_pairs(&problem, &ORTHOGONAL_MOVES) {
+                        csp.add_forbidden_pair_clause(a, b, |a, b| a.abs_diff(b) == 1)?;
                     }
                 }
             }
         }
+        csp.problem = problem;
         Ok(csp)
     }
+
+    fn add_group_clauses(
+        &mut self,
+        index: usize,
+        cg: &ConstrainedGroup,
+        cells: Vec<Cell>,
+        distinct_groups: &HashMap<Cell, Vec<usize>>,
+    ) -> Result<(), GridCspError> {
+        let Some(weight) = cg.weight else {
+            return self.add_constraint_clauses(cg, cells, distinct_groups);
+        };
+        let relaxation = self.alloc_var()?;
+        self.relaxation = Some(relaxation);
+        let added = self.add_constraint_clauses(cg, cells, distinct_groups);
+        self.relaxation = None;
+        self.soft_constraints.push((index, relaxation, weight));
+        added
+    }
+
+    fn add_constraint_clauses(
+        &mut self,
+        cg: &ConstrainedGroup,
+        cells: Vec<Cell>,
+        distinct_groups: &HashMap<Cell, Vec<usize>>,
+    ) -> Result<(), GridCspError> {
+        let constraint = &cg.constraint;
+        match *constraint {
+            Constraint::Add(v) => {
+                let alternatives = add_enumeration_size(v, cells.len(), self.number_max);
+                if self.prefers_sum_clause(alternatives) {
+                    self.add_sum_clause(cells, v)?;
+                } else {
+                    let repeats = max_repeats(&cells, distinct_groups);
+                    let solutions = add_enumerator(v, cells.len(), self.number_max)
+                        .filter(|s| fits_repeats(s, repeats));
+                    self.add_alternative_clause(cells, solutions)?;
+                }
+            }
+            Constraint::ArrowSum { circle } => {
+                self.add_arrow_clause(circle, cells)?;
+            }
+            Constraint::Consecutive | Constraint::PairSum(_) | Constraint::Ratio(_) => {
+                self.add_pair_clause(cells[0], cells[1], |a, b| constraint.pair_holds(a, b))?;
+            }
+            Constraint::Div(v) => {
+                let repeats = max_repeats(&cells, distinct_groups);
+                let solutions = div_enumerator(v, cells.len(), self.number_max)
+                    .filter(|s| fits_repeats(s, repeats));
+                self.add_alternative_clause(cells, solutions)?;
+            }
+            Constraint::Count { value, min, max } => {
+                let vars: Vec<i32> = cells
+                    .iter()
+                    .map(|c| self.get_value_var(c, value))
+                    .try_collect()?;
+                self.add_cardinality_clause(vars, min, max)?;
+            }
+            Constraint::Equal(v) => {
+                for cell in cells.iter() {
+                    for literal in self.value_literals(*cell, v) {
+                        self.add_alo_clause([literal]);
+                    }
+                }
+            }
+            Constraint::Line(line) => self.add_line_clause(cells, line)?,
+            Constraint::MaxRun(length) => {
+                // Runs are checked along the whole line, holes included
+                let line = cg.group.to_cells(self.problem.grid);
+                self.add_max_run_clause(line, length)?;
+            }
+            Constraint::Mul(v) => {
+                let repeats = max_repeats(&cells, distinct_groups);
+                let solutions = mul_enumerator(v, cells.len(), self.number_max)
+                    .filter(|s| fits_repeats(s, repeats));
+                self.add_alternative_clause(cells, solutions)?;
+            }
+            Constraint::Runs(ref runs) => {
+                let line = cg.group.to_cells(self.problem.grid);
+                self.add_runs_clause(line, runs)?;
+            }
+            Constraint::Sandwich { low, high, sum } => {
+                self.add_sandwich_clause(cells, low, high, sum)?;
+            }
+            Constraint::Sub(v) => {
+                let repeats = max_repeats(&cells, distinct_groups);
+                let solutions = sub_enumerator(v, cells.len(), self.number_max)
+                    .filter(|s| fits_repeats(s, repeats));
+                self.add_alternative_clause(cells, solutions)?;
+            }
+            Constraint::UniqueAdd(v) => {
+                self.add_unique_clause(&cells)?;
+                // Only sets of distinct values are enumerated, which are far fewer than multisets
+                let alternatives = unique_add_enumeration_size(v, cells.len(), self.number_max);
+                if self.prefers_sum_clause(alternatives) {
+                    self.add_sum_clause(cells, v)?;
+                } else {
+                    let solutions = unique_add_enumerator(v, cells.len(), self.number_max);
+                    self.add_alternative_clause(cells, solutions)?;
+                }
+            }
+            Constraint::Visible(v) => {
+                self.add_visible_clause(cells, v)?;
+            }
+            Constraint::Unique => self.add_unique_clause(cells)?,
+        }
+        Ok(())
+    }
+
+    pub fn optimize(&mut self, objective: &Objective) -> Result<Optimum, GridCspError> {
+        objective.validate(&self.problem)?;
+        let sign = match objective {
+            Objective::Maximize(_) => -1,
+            Objective::Minimize(_) => 1,
+        };
+        let mut costs = HashMap::<Cell, Vec<i64>>::new();
+        for term in objective.terms() {
+            for cell in self.problem.group_cells(term.group()) {
+                let cell_costs = costs.entry(cell).or_default();
+                cell_costs.resize((self.number_max - self.number_min + 1) as usize, 0);
+                for (offset, value) in (self.number_min..=self.number_max).enumerate() {
+                    cell_costs[offset] = term
+                        .weight(value)?
+                        .checked_mul(sign)
+                        .and_then(|weight| cell_costs[offset].checked_add(weight))
+                        .ok_or(GridCspError::CostOverflow)?;
+                }
+            }
+        }
+        // Shifting the costs of each cell by their minimum makes them all nonnegative
+        let mut shifted_costs = Vec::with_capacity(costs.len());
+        for (cell, cell_costs) in costs.into_iter().sorted_by_key(|(c, _)| (c.x, c.y)) {
+            let min = cell_costs.iter().min().copied().unwrap_or(0);
+            let shifted: Vec<usize> = cell_costs
+                .iter()
+                .map(|c| {
+                    c.checked_sub(min)
+                        .and_then(|c| usize::try_from(c).ok())
+                        .ok_or(GridCspError::CostOverflow)
+                })
+                .try_collect()?;
+            if shifted.iter().any(|c| *c > 0) {
+                shifted_costs.push((cell, shifted));
+            }
+        }
+        let mut groups = Vec::with_capacity(shifted_costs.len());
+        for (cell, cell_costs) in shifted_costs.iter() {
+            let mut group = Vec::new();
+            for (offset, cost) in cell_costs.iter().enumerate() {
+                if *cost > 0 {
+                    group.push((
+                        self.get_value_var(cell, self.number_min + offset as u64)?,
+                        *cost,
+                    ));
+                }
+            }
+            groups.push(group);
+        }
+        let model = self.minimize(&groups)?;
+        let solution = self.decode(&model)?;
+        let value = objective.evaluate(&self.problem, &solution)?;
+        Ok(Optimum { solution, value })
+    }
+
+    // Soft constraints are violated as little as possible, by weight
+    pub fn solve_soft(&mut self) -> Result<SoftSolution, GridCspError> {
+        let groups: Vec<Vec<(i32, usize)>> = self
+            .soft_constraints
+            .iter()
+            .map(|(_, relaxation, weight)| {
+                usize::try_from(*weight)
+                    .map(|weight| vec![(*relaxation, weight)])
+                    .map_err(|_| GridCspError::CostOverflow)
+            })
+            .try_collect()?;
+        let model = self.minimize(&groups)?;
+        let violated: Vec<(usize, u64)> = self
+            .soft_constraints
+            .iter()
+            .filter(|(_, relaxation, _)| model[*relaxation as usize - 1] > 0)
+            .map(|(index, _, weight)| (*index, *weight))
+            .collect();
+        let violated_weight = violated
+            .iter()
+            .try_fold(0u64, |acc, (_, weight)| acc.checked_add(*weight))
+            .ok_or(GridCspError::CostOverflow)?;
+        Ok(SoftSolution {
+            solution: self.decode(&model)?,
+            violated_weight,
+            violated: violated.into_iter().map(|(index, _)| index).collect(),
+        })
+    }
+
+    // Each group holds vars with their cost, at most one var of a group being true. The cost is
+    // bounded in binary, halving the gap between the best model and the costs proven out of
+    // reach until it is closed, which proves the best model optimal
+    fn minimize(&mut self, groups: &[Vec<(i32, usize)>]) -> Result<Vec<i32>, GridCspError> {
+        let divisor = groups
+            .iter()
+            .flatten()
+            .fold(0, |acc, (_, cost)| gcd(acc as u64, *cost as u64) as usize)
+            .max(1);
+        let groups: Vec<Vec<(i32, usize)>> = groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|(var, cost)| (*var, cost / divisor))
+                    .collect()
+            })
+            .collect();
+        let cost = |model: &[i32]| -> Result<usize, GridCspError> {
+            groups
+                .iter()
+                .flatten()
+                .filter(|(literal, _)| model[literal.unsigned_abs() as usize - 1] == *literal)
+                .try_fold(0usize, |acc, (_, cost)| {
+                    acc.checked_add(*cost).ok_or(GridCspError::CostOverflow)
+                })
+        };
+
+        let mut model = self.search(&[])?;
+        let mut upper = cost(&model)?;
+        if upper > 0 {
+            // Bounds only hold for this search
+            self.push()?;
+            let searched = self.add_cost_clause(&groups).and_then(|sum| {
+                // Costs below it are out of reach
+                let mut lower = 0;
+                while lower < upper {
+                    let bound = lower + (upper - lower) / 2;
+                    let selector = self.alloc_var()?;
+                    self.add_at_most_clause(&sum, bound, selector);
+                    match self.search(&[vec![selector]]) {
+                        Ok(better) => {
+                            upper = cost(&better)?;
+                            model = better;
+                        }
+                        Err(GridCspError::NoSolution) => lower = bound + 1,
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(())
+            });
+            self.pop()?;
+            searched?;
+        }
+        Ok(model)
+    }
+
+    // Bits of the summed group costs, each group adding the bits of the cost of its true var
+    fn add_cost_clause(&mut self, groups: &[Vec<(i32, usize)>]) -> Result<Vec<i32>, GridCspError> {
+        let mut numbers = Vec::with_capacity(groups.len());
+        for group in groups.iter() {
+            let max = group.iter().map(|(_, cost)| *cost).max().unwrap_or(0);
+            let bits: Vec<i32> = (0..usize::BITS - max.leading_zeros())
+                .map(|_| self.alloc_var())
+                .try_collect()?;
+            for (b, bit) in bits.iter().enumerate() {
+                let literals: Vec<i32> = group
+                    .iter()
+                    .filter(|(_, cost)| cost >> b & 1 == 1)
+                    .map(|(literal, _)| *literal)
+                    .collect();
+                for literal in literals.iter() {
+                    self.add_clause(vec![-literal, *bit]);
+                }
+                self.add_clause([-bit].into_iter().chain(literals).collect());
+            }
+            numbers.push(bits);
+        }
+        self.add_adder_tree(numbers)
+    }
+
+    // Bounds the binary number from above while the selector holds: a bit set where the bound
+    // has none requires a higher bit of the bound to be unset
+    fn add_at_most_clause(&mut self, bits: &[i32], bound: usize, selector: i32) {
+        let bound_bit = |b: usize| bound.checked_shr(b as u32).is_some_and(|v| v & 1 == 1);
+        for (b, bit) in bits.iter().enumerate().filter(|(b, _)| !bound_bit(*b)) {
+            let mut clause = vec![-selector, -bit];
+            clause.extend(
+                bits.iter()
+                    .enumerate()
+                    .skip(b + 1)
+                    .filter(|(c, _)| bound_bit(*c))
+                    .map(|(_, higher)| -higher),
+            );
+            self.add_clause(clause);
+        }
+    }
+
+    pub fn add_constrained_group(&mut self, cg: ConstrainedGroup) -> Result<(), GridCspError> {
+        cg.validate(&self.problem)?;
+        // Negative rules were encoded for the pairs constrained at the time
+        if cg.constraint.is_pairwise()
+            && self
+                .problem
+                .rules
+                .iter()
+                .any(|r| matches!(r, GlobalRule::Negative(_)))
+        {
+            return Err(GridCspError::UnsupportedConstraint);
+        }
+        let index = self.problem.constraints.len();
+        let cells = self.problem.group_cells(&cg.group);
+        self.problem.add_constraint(cg.clone());
+        let distinct_groups = distinct_groups(&self.problem);
+        let added = self.add_group_clauses(index, &cg, cells, &distinct_groups);
+        if added.is_err() {
+            self.problem.constraints.pop();
+        }
+        added
+    }
+
+    // Clauses added until the matching pop only hold while the scope is open
+    pub fn push(&mut self) -> Result<(), GridCspError> {
+        let activation = self.alloc_var()?;
+        self.scopes.push((
+            activation,
+            self.problem.constraints.len(),
+            self.clauses.len(),
+        ));
+        Ok(())
+    }
+
+    // The clauses of the scope are dropped from those the solver would be rebuilt from, while
+    // the persistent solver switches them off for good. It is only rebuilt once it holds enough
+    // dead scopes. Vars of the scope are left unused
+    pub fn pop(&mut self) -> Result<(), GridCspError> {
+        let (activation, constraint_count, clause_count) =
+            self.scopes.pop().ok_or(GridCspError::NoScope)?;
+        let is_scoped = |clause: &Vec<i32>| clause.contains(&-activation);
+        let loaded = self.loaded_clauses.clamp(clause_count, self.clauses.len());
+        let loaded_scoped = self.clauses[clause_count..loaded]
+            .iter()
+            .filter(|clause| is_scoped(clause))
+            .count();
+        let scoped = self.clauses.split_off(clause_count);
+        self.clauses
+            .extend(scoped.into_iter().filter(|clause| !is_scoped(clause)));
+        if loaded_scoped > 0 && self.solver.is_some() {
+            self.loaded_clauses -= loaded_scoped;
+            self.dead_scopes += 1;
+            let switched_off = self.dead_scopes <= DEAD_SCOPE_LIMIT
+                && self.solver.as_mut().is_some_and(|solver| {
+                    let added = solver.add_clause([-activation]).is_ok();
+                    solver.reset();
+                    added
+                });
+            if !switched_off {
+                self.solver = None;
+                self.dead_scopes = 0;
+            }
+        }
+        self.problem.constraints.truncate(constraint_count);
+        self.soft_constraints
+            .retain(|(index, _, _)| *index < constraint_count);
+        Ok(())
+    }
+}
+
+impl TryFrom<GenericProblem> for GridCspSolver {
+    type Error = GridCspError;
+
+    fn try_from(problem: GenericProblem) -> Result<Self, Self::Error> {
+        GridCspSolver::from_problem(problem, SolverOptions::default())
+    }
+}
+
+// Hard constraints each cell shares with others holding distinct values
+fn distinct_groups(problem: &GenericProblem) -> HashMap<Cell, Vec<usize>> {
+    let mut distinct_groups = HashMap::<Cell, Vec<usize>>::new();
+    for (i, cg) in problem.constraints.iter().enumerate() {
+        if cg.weight.is_none()
+            && matches!(
+                cg.constraint,
+                Constraint::Unique
+                    | Constraint::UniqueAdd(_)
+                    | Constraint::Line(LineConstraint::Renban)
+            )
+        {
+            for cell in problem.group_cells(&cg.group) {
+                distinct_groups.entry(cell).or_default().push(i);
+            }
+        }
+    }
+    distinct_groups
+}
+
+// Most cells of the group that may hold the same value, as cells sharing a group of distinct
+// values never do, only computed for small groups
+fn max_repeats(cells: &[Cell], distinct_groups: &HashMap<Cell, Vec<usize>>) -> usize {
+    if cells.len() > 16 {
+        return cells.len();
+    }
+    let shares_group = |a: &Cell, b: &Cell| {
+        let (Some(a), Some(b)) = (distinct_groups.get(a), distinct_groups.get(b)) else {
+            return false;
+        };
+        a.iter().any(|g| b.contains(g))
+    };
+    let conflicts: Vec<u32> = cells
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|(j, b)| i != *j && shares_group(a, b))
+                .fold(0, |mask, (j, _)| mask | 1 << j)
+        })
+        .collect();
+    (0u32..1 << cells.len())
+        .filter(|set| (0..cells.len()).all(|i| set >> i & 1 == 0 || conflicts[i] & set == 0))
+        .map(|set| set.count_ones() as usize)
+        .max()
+        .unwrap_or(0)
+}
+
+fn fits_repeats(solution: &[u64], repeats: usize) -> bool {
+    solution
+        .iter()
+        .dedup_with_count()
+        .all(|(count, _)| count <= repeats)
+}
+
+// Clauses of a totalizer over count leaves of the given size, each node merging a and b
+// values checking every pair of partial sums with up to two clauses
+fn totalizer_size(count: usize, size: usize, cap: usize) -> u64 {
+    let mut nodes = vec![size.min(cap) as u64; count];
+    let mut clauses = 0u64;
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => {
+                    let pairs = (a + 1).saturating_mul(b + 1);
+                    clauses = clauses.saturating_add(pairs.saturating_mul(2));
+                    a.saturating_add(*b).min(cap as u64)
+                }
+                _ => pair[0],
+            })
+            .collect();
+    }
+    clauses
+}
+
+// Splr reports some unsatisfiable problems as errors, a unit clause contradicting the root
+// assignments being Inconsistent
+fn solver_error(err: SolverError) -> GridCspError {
+    match err {
+        SolverError::EmptyClause
+        | SolverError::RootLevelConflict(_)
+        | SolverError::Inconsistent => GridCspError::NoSolution,
+        err => GridCspError::SolverError(err),
+    }
+}
+
+// Literals of the bits making up the offset
+fn bit_literals(bits: &[i32], offset: usize) -> impl Iterator<Item = i32> + '_ {
+    bits.iter()
+        .enumerate()
+        .map(move |(b, bit)| if offset >> b & 1 == 1 { *bit } else { -bit })
+}
+
+// Half of each move set, so that every unordered pair of cells is produced once
+const ORTHOGONAL_MOVES: [(isize, isize); 2] = [(1, 0), (0, 1)];
+const KING_MOVES: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+const KNIGHT_MOVES: [(isize, isize); 4] = [(1, 2), (2, 1), (1, -2), (2, -1)];
+
+fn neighbour_pairs<'a>(
+    problem: &'a GenericProblem,
+    moves: &'a [(isize, isize)],
+) -> impl Iterator<Item = (Cell, Cell)> + 'a {
+    problem.active_cells().flat_map(move |c| {
+        moves.iter().filter_map(move |(dx, dy)| {
+            let x = c.x.checked_add_signed(*dx)?;
+            let y = c.y.checked_add_signed(*dy)?;
+            let n = Cell::new(x, y);
+            (x < problem.grid.width && y < problem.grid.height && problem.is_active(n))
+                .then_some((c, n))
+        })
+    })
 }
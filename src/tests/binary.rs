@@ -0,0 +1,91 @@
+use super::full_grid;
+use crate::error::GridCspError;
+use crate::model::*;
+use crate::sat::{Encoding, GridCspSolver, SolverOptions};
+
+#[test]
+fn binary_6x6() -> Result<(), GridCspError> {
+    let problem: BinaryPuzzleProblem = serde_json::from_str(
+        r#"{
+            "grid_size": 6,
+            "constraints": [
+                {
+                    "constraint": { "Equal": 0 },
+                    "group": { "List": [{ "x": 0, "y": 2 }, { "x": 1, "y": 1 }, { "x": 3, "y": 2 }, { "x": 3, "y": 5 }] }
+                },
+                {
+                    "constraint": { "Equal": 1 },
+                    "group": { "List": [{ "x": 0, "y": 0 }, { "x": 4, "y": 3 }, { "x": 5, "y": 0 }, { "x": 5, "y": 1 }] }
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let problem = GenericProblem::try_from(problem)?;
+    for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+        let options = SolverOptions::default().with_encoding(encoding);
+        let mut csp = GridCspSolver::from_problem(problem.clone(), options)?;
+        let solution = full_grid(csp.solve_unique()?);
+        assert_eq!(
+            solution,
+            vec![
+                vec![1, 0, 0, 1, 0, 1],
+                vec![0, 0, 1, 0, 1, 1],
+                vec![0, 1, 1, 0, 1, 0],
+                vec![1, 1, 0, 1, 0, 0],
+                vec![0, 0, 1, 1, 0, 1],
+                vec![1, 1, 0, 0, 1, 0]
+            ]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn binary_odd_size() {
+    assert_eq!(
+        GenericProblem::try_from(BinaryPuzzleProblem::new(5)).err(),
+        Some(GridCspError::UnsupportedBinaryPuzzleSize)
+    );
+}
+
+#[test]
+fn binary_arithmetic_constraint() {
+    let mut problem = BinaryPuzzleProblem::new(4);
+    problem.add_constraint(Constraint::Add(1).over(CellGroup::Row(0)));
+    let problem = GenericProblem::try_from(problem).unwrap();
+    assert_eq!(problem.validate(), Err(GridCspError::UnsupportedConstraint));
+}
+
+#[test]
+fn binary_masked_max_run() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(5, 1, 1).with_number_min(0));
+    problem.deactivate(Cell::new(2, 0));
+    problem.add_constraint(Constraint::MaxRun(2).over(CellGroup::Row(0)));
+    problem.add_constraint(Constraint::MaxRun(usize::MAX).over(CellGroup::Row(0)));
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::Row(0)));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let solution = csp.solve_unique()?;
+    assert_eq!(
+        solution,
+        vec![
+            vec![Some(1)],
+            vec![Some(1)],
+            vec![None],
+            vec![Some(1)],
+            vec![Some(1)]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn binary_distinct_rows_without_overlap() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(2, 2, 1).with_number_min(0));
+    problem.deactivate(Cell::new(1, 0));
+    problem.deactivate(Cell::new(0, 1));
+    problem.add_rule(GlobalRule::DistinctRows);
+    let mut csp = GridCspSolver::try_from(problem)?;
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use super::full_grid;
+use crate::error::GridCspError;
+use crate::model::*;
+use crate::sat::GridCspSolver;
+
+#[test]
+fn hidato_4x3() -> Result<(), GridCspError> {
+    let problem: HidatoProblem = serde_json::from_str(
+        r#"{
+            "width": 4,
+            "height": 3,
+            "adjacency": "King",
+            "blocked": [],
+            "constraints": [
+                {
+                    "constraint": { "Equal": 1 },
+                    "group": { "List": [{ "x": 0, "y": 0 }] }
+                },
+                {
+                    "constraint": { "Equal": 4 },
+                    "group": { "List": [{ "x": 3, "y": 1 }] }
+                },
+                {
+                    "constraint": { "Equal": 10 },
+                    "group": { "List": [{ "x": 1, "y": 1 }] }
+                },
+                {
+                    "constraint": { "Equal": 11 },
+                    "group": { "List": [{ "x": 0, "y": 2 }] }
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    let solution = full_grid(csp.solve_unique()?);
+    assert_eq!(
+        solution,
+        vec![
+            vec![1, 12, 11],
+            vec![2, 10, 9],
+            vec![3, 6, 8],
+            vec![5, 4, 7]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn numbrix_4x4() -> Result<(), GridCspError> {
+    let mut problem = HidatoProblem::numbrix(4, 4);
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![Cell::new(0, 1)])));
+    problem.add_constraint(Constraint::Equal(6).over(CellGroup::List(vec![Cell::new(1, 1)])));
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    let solution = full_grid(csp.solve_unique()?);
+    assert_eq!(
+        solution,
+        vec![
+            vec![16, 1, 2, 3],
+            vec![15, 6, 5, 4],
+            vec![14, 7, 8, 9],
+            vec![13, 12, 11, 10]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn numbrix_with_hole() -> Result<(), GridCspError> {
+    let mut problem = HidatoProblem::numbrix(3, 3);
+    problem.block(Cell::new(1, 1));
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![Cell::new(0, 0)])));
+    problem.add_constraint(Constraint::Equal(2).over(CellGroup::List(vec![Cell::new(1, 0)])));
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    let solution = csp.solve_unique()?;
+    assert_eq!(
+        solution,
+        vec![
+            vec![Some(1), Some(8), Some(7)],
+            vec![Some(2), None, Some(6)],
+            vec![Some(3), Some(4), Some(5)]
+        ]
+    );
+    Ok(())
+}
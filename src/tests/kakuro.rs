@@ -0,0 +1,52 @@
+use crate::error::GridCspError;
+use crate::model::*;
+use crate::sat::GridCspSolver;
+
+#[test]
+fn kakuro_5x5() -> Result<(), GridCspError> {
+    let layout = ["#####", "#..##", "#...#", "#...#", "##..#"];
+    let mut problem = KakuroProblem::new(5, 5);
+    for (y, row) in layout.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c == '#' {
+                problem.block(Cell::new(x, y));
+            }
+        }
+    }
+    let runs = [
+        (vec![(1, 1), (2, 1)], 9),
+        (vec![(1, 2), (2, 2), (3, 2)], 24),
+        (vec![(1, 3), (2, 3), (3, 3)], 10),
+        (vec![(2, 4), (3, 4)], 5),
+        (vec![(1, 1), (1, 2), (1, 3)], 11),
+        (vec![(2, 1), (2, 2), (2, 3), (2, 4)], 27),
+        (vec![(3, 2), (3, 3), (3, 4)], 10),
+    ];
+    for (cells, sum) in runs {
+        problem.add_run(cells.iter().map(|(x, y)| Cell::new(*x, *y)).collect(), sum);
+    }
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    let solution = csp.solve_unique()?;
+    assert_eq!(
+        solution,
+        vec![
+            vec![None; 5],
+            vec![None, Some(1), Some(8), Some(2), None],
+            vec![None, Some(8), Some(9), Some(7), Some(3)],
+            vec![None, None, Some(7), Some(1), Some(2)],
+            vec![None; 5]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn kakuro_run_through_block() {
+    let mut problem = KakuroProblem::new(3, 3);
+    problem.block(Cell::new(1, 1));
+    problem.add_run(vec![Cell::new(0, 1), Cell::new(1, 1)], 3);
+    assert!(matches!(
+        GridCspSolver::try_from(GenericProblem::from(problem)),
+        Err(GridCspError::InactiveCell(1, 1))
+    ));
+}
@@ -1,6 +1,7 @@
+use super::full_grid;
 use crate::error::GridCspError;
 use crate::model::*;
-use crate::sat::GridCspSolver;
+use crate::sat::{GridCspSolver, SolverOptions};
 
 #[test]
 fn kenken_3x3() -> Result<(), GridCspError> {
@@ -33,7 +34,7 @@ fn kenken_3x3() -> Result<(), GridCspError> {
     )
     .unwrap();
     let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
-    let solution = csp.solve_unique()?;
+    let solution = full_grid(csp.solve_unique()?);
     assert_eq!(solution, vec![vec![3, 1, 2], vec![2, 3, 1], vec![1, 2, 3]]);
     Ok(())
 }
@@ -77,7 +78,7 @@ fn kenken_4x4() -> Result<(), GridCspError> {
     )
     .unwrap();
     let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
-    let solution = csp.solve_unique()?;
+    let solution = full_grid(csp.solve_unique()?);
     assert_eq!(
         solution,
         vec![
@@ -153,7 +154,7 @@ fn kenken_5x5() -> Result<(), GridCspError> {
     )
     .unwrap();
     let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
-    let solution = csp.solve_unique()?;
+    let solution = full_grid(csp.solve_unique()?);
     assert_eq!(
         solution,
         vec![
@@ -166,3 +167,119 @@ fn kenken_5x5() -> Result<(), GridCspError> {
     );
     Ok(())
 }
+
+#[test]
+fn kenken_enumeration_limit() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(6, 1, 6));
+    problem.add_constraint(Constraint::Mul(720).over(CellGroup::Row(0)));
+    let options = SolverOptions::default().with_enumeration_limit(5);
+    assert!(matches!(
+        GridCspSolver::from_problem(problem.clone(), options),
+        Err(GridCspError::ConstraintTooLarge)
+    ));
+    let options = SolverOptions::default().with_enumeration_limit(6);
+    let mut csp = GridCspSolver::from_problem(problem, options)?;
+    let solution = full_grid(csp.solve()?);
+    assert_eq!(solution.iter().flatten().product::<u64>(), 720);
+    // A rejected constraint leaves nothing behind
+    let cells = (0..4).map(|x| Cell::new(x, 0)).collect();
+    assert_eq!(
+        csp.add_constrained_group(Constraint::Add(14).over(CellGroup::List(cells))),
+        Err(GridCspError::ConstraintTooLarge)
+    );
+    let solution = full_grid(csp.solve()?);
+    assert_eq!(solution.iter().flatten().product::<u64>(), 720);
+    Ok(())
+}
+
+#[test]
+fn kenken_cage_repeats() -> Result<(), GridCspError> {
+    let mut problem = KenKenProblem::new(4);
+    problem.add_constraint(Constraint::Mul(4).over(CellGroup::List(vec![
+        Cell::new(0, 0),
+        Cell::new(1, 0),
+        Cell::new(2, 0),
+    ])));
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+
+    let mut problem = KenKenProblem::new(2);
+    problem.add_constraint(Constraint::Add(6).over(CellGroup::Square {
+        x: 0,
+        y: 0,
+        height: 2,
+        width: 2,
+    }));
+    problem.add_constraint(Constraint::Equal(2).over(CellGroup::List(vec![Cell::new(0, 0)])));
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    let solution = full_grid(csp.solve_unique()?);
+    assert_eq!(solution, vec![vec![2, 1], vec![1, 2]]);
+    Ok(())
+}
+
+#[test]
+fn kenken_huge_target() -> Result<(), GridCspError> {
+    let mut problem = KenKenProblem::new(4);
+    problem.add_constraint(
+        Constraint::Add(1_000_000_000)
+            .over(CellGroup::List(vec![Cell::new(0, 0), Cell::new(1, 0)])),
+    );
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+    Ok(())
+}
+
+#[test]
+fn kenken_scopes() -> Result<(), GridCspError> {
+    let cage = |constraint: Constraint, cells: &[(usize, usize)]| {
+        constraint.over(CellGroup::List(
+            cells.iter().map(|(x, y)| Cell::new(*x, *y)).collect(),
+        ))
+    };
+    let mut problem = KenKenProblem::new(4);
+    problem.add_constraint(cage(Constraint::Equal(4), &[(3, 3)]));
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    assert_eq!(csp.solve_unique(), Err(GridCspError::SolutionNotUnique));
+
+    csp.push()?;
+    csp.add_constrained_group(cage(Constraint::Mul(12), &[(0, 0), (1, 0)]))?;
+    csp.add_constrained_group(cage(Constraint::Mul(2), &[(2, 0), (3, 0), (3, 1)]))?;
+    csp.add_constrained_group(cage(Constraint::Div(4), &[(0, 1), (0, 2)]))?;
+    csp.add_constrained_group(cage(Constraint::Mul(6), &[(1, 1), (1, 2)]))?;
+    csp.add_constrained_group(cage(Constraint::Mul(72), &[(2, 1), (2, 2), (2, 3), (3, 2)]))?;
+    csp.add_constrained_group(cage(Constraint::Div(2), &[(0, 3), (1, 3)]))?;
+    let solution = full_grid(csp.solve_unique()?);
+    assert_eq!(
+        solution,
+        vec![
+            vec![3, 4, 1, 2],
+            vec![4, 3, 2, 1],
+            vec![1, 2, 4, 3],
+            vec![2, 1, 3, 4]
+        ]
+    );
+    csp.push()?;
+    csp.add_constrained_group(cage(Constraint::Equal(1), &[(3, 3)]))?;
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+    csp.pop()?;
+    assert_eq!(full_grid(csp.solve_unique()?), solution);
+    csp.pop()?;
+    assert_eq!(csp.solve_unique(), Err(GridCspError::SolutionNotUnique));
+    assert_eq!(csp.pop(), Err(GridCspError::NoScope));
+
+    // An editor adding and withdrawing a clue after each keystroke, past the point where the
+    // solver is rebuilt without the dead scopes
+    for value in (1..=4).cycle().take(150) {
+        csp.push()?;
+        csp.add_constrained_group(cage(Constraint::Equal(value), &[(0, 0)]))?;
+        assert_eq!(csp.solve()?[0][0], Some(value));
+        csp.pop()?;
+    }
+    assert_eq!(csp.solve_unique(), Err(GridCspError::SolutionNotUnique));
+
+    assert_eq!(
+        csp.add_constrained_group(cage(Constraint::Equal(5), &[(0, 0)])),
+        Err(GridCspError::ValueOutOfBound(5))
+    );
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use super::full_grid;
+use crate::error::GridCspError;
+use crate::model::*;
+use crate::sat::GridCspSolver;
+
+const SOLUTION: [[u64; 6]; 6] = [
+    [3, 6, 5, 1, 4, 2],
+    [4, 1, 6, 5, 2, 3],
+    [6, 4, 3, 2, 1, 5],
+    [5, 3, 2, 4, 6, 1],
+    [1, 2, 4, 3, 5, 6],
+    [2, 5, 1, 6, 3, 4],
+];
+
+fn dot(constraint: Constraint, a: (usize, usize), b: (usize, usize)) -> ConstrainedGroup {
+    constraint.over(CellGroup::List(vec![
+        Cell::new(a.0, a.1),
+        Cell::new(b.0, b.1),
+    ]))
+}
+
+#[test]
+fn kropki_6x6() -> Result<(), GridCspError> {
+    let white = [
+        ((0, 0), (1, 0)),
+        ((0, 1), (0, 2)),
+        ((0, 2), (1, 2)),
+        ((0, 5), (1, 5)),
+        ((1, 2), (1, 3)),
+        ((1, 4), (2, 4)),
+        ((1, 4), (1, 5)),
+        ((2, 0), (3, 0)),
+        ((2, 1), (3, 1)),
+        ((2, 1), (2, 2)),
+        ((2, 2), (3, 2)),
+        ((2, 2), (2, 3)),
+        ((2, 3), (2, 4)),
+        ((3, 1), (4, 1)),
+        ((3, 1), (3, 2)),
+        ((3, 3), (4, 3)),
+        ((3, 4), (4, 4)),
+        ((4, 0), (5, 0)),
+        ((4, 0), (4, 1)),
+        ((4, 2), (4, 3)),
+        ((4, 4), (4, 5)),
+        ((5, 4), (5, 5)),
+    ];
+    let black = [
+        ((0, 0), (0, 1)),
+        ((0, 4), (1, 4)),
+        ((0, 4), (0, 5)),
+        ((1, 2), (2, 2)),
+        ((2, 3), (3, 3)),
+        ((3, 2), (4, 2)),
+        ((3, 2), (3, 3)),
+        ((4, 1), (4, 2)),
+        ((4, 3), (5, 3)),
+        ((5, 3), (5, 4)),
+    ];
+    let mut problem = GenericProblem::from(KenKenProblem::new(6));
+    for (a, b) in white {
+        problem.add_constraint(dot(Constraint::Consecutive, a, b));
+    }
+    for (a, b) in black {
+        problem.add_constraint(dot(Constraint::Ratio(2), a, b));
+    }
+    problem.add_rule(GlobalRule::Negative(Constraint::Consecutive));
+    problem.add_rule(GlobalRule::Negative(Constraint::Ratio(2)));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let solution = full_grid(csp.solve_unique()?);
+    assert_eq!(solution, SOLUTION.map(Vec::from).to_vec());
+    Ok(())
+}
+
+#[test]
+fn xv_6x6() -> Result<(), GridCspError> {
+    let v = [
+        ((0, 3), (0, 4)),
+        ((0, 5), (1, 5)),
+        ((1, 0), (1, 1)),
+        ((1, 1), (2, 1)),
+        ((1, 4), (1, 5)),
+        ((2, 2), (3, 2)),
+        ((2, 2), (2, 3)),
+        ((3, 1), (4, 1)),
+        ((3, 1), (3, 2)),
+        ((4, 2), (5, 2)),
+    ];
+    let x_dots = [
+        ((1, 0), (2, 0)),
+        ((2, 0), (2, 1)),
+        ((3, 3), (3, 4)),
+        ((4, 5), (5, 5)),
+    ];
+    let mut problem = GenericProblem::from(KenKenProblem::new(6));
+    for (a, b) in v {
+        problem.add_constraint(dot(Constraint::PairSum(5), a, b));
+    }
+    for (a, b) in x_dots {
+        problem.add_constraint(dot(Constraint::PairSum(10), a, b));
+    }
+    problem.add_rule(GlobalRule::Negative(Constraint::PairSum(5)));
+    problem.add_rule(GlobalRule::Negative(Constraint::PairSum(10)));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let solution = full_grid(csp.solve()?);
+    for x in 0..6 {
+        for y in 0..6 {
+            for (a, b) in [((x, y), (x + 1, y)), ((x, y), (x, y + 1))] {
+                if b.0 == 6 || b.1 == 6 {
+                    continue;
+                }
+                let sum = solution[a.0][a.1] + solution[b.0][b.1];
+                assert_eq!(sum == 5, v.contains(&(a, b)));
+                assert_eq!(sum == 10, x_dots.contains(&(a, b)));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn kropki_invalid_rule() {
+    let mut problem = GenericProblem::from(KenKenProblem::new(3));
+    problem.add_rule(GlobalRule::Negative(Constraint::Unique));
+    assert!(matches!(
+        GridCspSolver::try_from(problem),
+        Err(GridCspError::UnsupportedGlobalRule)
+    ));
+}
+
+#[test]
+fn kropki_dot_not_adjacent() {
+    for (a, b) in [((0, 0), (0, 0)), ((0, 0), (1, 1)), ((0, 0), (0, 2))] {
+        let mut problem = GenericProblem::from(KenKenProblem::new(3));
+        problem.add_constraint(dot(Constraint::Consecutive, a, b));
+        assert_eq!(
+            GridCspSolver::try_from(problem).err(),
+            Some(GridCspError::CellsNotAdjacent(
+                Cell::new(a.0, a.1),
+                Cell::new(b.0, b.1)
+            ))
+        );
+    }
+}
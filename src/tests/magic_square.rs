@@ -1,6 +1,9 @@
+use super::full_grid;
 use crate::error::GridCspError;
-use crate::model::{GenericProblem, MagicSquareProblem};
-use crate::sat::GridCspSolver;
+use crate::model::{
+    Cell, CellGroup, Constraint, GenericProblem, GridDimensions, MagicSquareProblem,
+};
+use crate::sat::{AmoEncoding, Encoding, GridCspSolver, SolverOptions, SumEncoding};
 
 #[test]
 fn magic_square_2x2() -> Result<(), GridCspError> {
@@ -35,7 +38,7 @@ fn magic_square_3x3() -> Result<(), GridCspError> {
     )
     .unwrap();
     let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
-    let solution = csp.solve_unique()?;
+    let solution = full_grid(csp.solve_unique()?);
     assert_eq!(solution, vec![vec![4, 3, 8], vec![9, 5, 1], vec![2, 7, 6]]);
     Ok(())
 }
@@ -53,3 +56,94 @@ fn magic_square_4x4() -> Result<(), GridCspError> {
     csp.solve()?;
     Ok(())
 }
+
+#[test]
+fn magic_square_3x3_amo_encodings() -> Result<(), GridCspError> {
+    for amo_encoding in [
+        AmoEncoding::Auto,
+        AmoEncoding::Bimander,
+        AmoEncoding::Commander,
+        AmoEncoding::Pairwise,
+        AmoEncoding::Product,
+        AmoEncoding::Sequential,
+    ] {
+        let mut problem = MagicSquareProblem::new(3);
+        problem.add_constraint(Constraint::Equal(4).over(CellGroup::List(vec![Cell::new(0, 0)])));
+        problem.add_constraint(Constraint::Equal(2).over(CellGroup::List(vec![Cell::new(2, 0)])));
+        let options = SolverOptions::default().with_amo_encoding(amo_encoding);
+        let mut csp = GridCspSolver::from_problem(GenericProblem::from(problem), options)?;
+        let solution = full_grid(csp.solve_unique()?);
+        assert_eq!(solution, vec![vec![4, 3, 8], vec![9, 5, 1], vec![2, 7, 6]]);
+    }
+    Ok(())
+}
+
+#[test]
+fn magic_square_3x3_encodings() -> Result<(), GridCspError> {
+    for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+        let mut problem = MagicSquareProblem::new(3);
+        problem.add_constraint(Constraint::Equal(4).over(CellGroup::List(vec![Cell::new(0, 0)])));
+        problem.add_constraint(Constraint::Equal(2).over(CellGroup::List(vec![Cell::new(2, 0)])));
+        let options = SolverOptions::default().with_encoding(encoding);
+        let mut csp = GridCspSolver::from_problem(GenericProblem::from(problem), options)?;
+        let solution = full_grid(csp.solve_unique()?);
+        assert_eq!(solution, vec![vec![4, 3, 8], vec![9, 5, 1], vec![2, 7, 6]]);
+    }
+    Ok(())
+}
+
+#[test]
+fn magic_square_3x3_sum_encodings() -> Result<(), GridCspError> {
+    for sum_encoding in [
+        SumEncoding::Enumeration,
+        SumEncoding::Totalizer,
+        SumEncoding::Adder,
+    ] {
+        for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+            let mut problem = MagicSquareProblem::new(3);
+            problem
+                .add_constraint(Constraint::Equal(4).over(CellGroup::List(vec![Cell::new(0, 0)])));
+            problem
+                .add_constraint(Constraint::Equal(2).over(CellGroup::List(vec![Cell::new(2, 0)])));
+            let options = SolverOptions::default()
+                .with_encoding(encoding)
+                .with_sum_encoding(sum_encoding);
+            let mut csp = GridCspSolver::from_problem(GenericProblem::from(problem), options)?;
+            let solution = full_grid(csp.solve_unique()?);
+            assert_eq!(solution, vec![vec![4, 3, 8], vec![9, 5, 1], vec![2, 7, 6]]);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn large_sum_row() -> Result<(), GridCspError> {
+    // The totalizer would grow with the square of the sum, so it is added in binary
+    for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+        let mut problem = GenericProblem::new(GridDimensions::new(10, 1, 100));
+        problem.add_constraint(Constraint::UniqueAdd(505).over(CellGroup::Row(0)));
+        for x in 0..9 {
+            let value = if x < 8 { 10 * (x as u64 + 1) } else { 100 };
+            problem.add_constraint(
+                Constraint::Equal(value).over(CellGroup::List(vec![Cell::new(x, 0)])),
+            );
+        }
+        let options = SolverOptions::default().with_encoding(encoding);
+        let mut csp = GridCspSolver::from_problem(problem, options)?;
+        assert_eq!(csp.solve_unique()?[9][0], Some(45));
+    }
+    Ok(())
+}
+
+#[test]
+fn magic_square_16x16_totalizer_limit() -> Result<(), GridCspError> {
+    let problem = GenericProblem::from(MagicSquareProblem::new(16));
+    // Each line sums to 2056, far too much for a totalizer
+    let options = SolverOptions::default().with_sum_encoding(SumEncoding::Totalizer);
+    assert!(matches!(
+        GridCspSolver::from_problem(problem.clone(), options),
+        Err(GridCspError::ConstraintTooLarge)
+    ));
+    GridCspSolver::from_problem(problem, SolverOptions::default())?;
+    Ok(())
+}
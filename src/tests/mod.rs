@@ -1,4 +1,20 @@
+mod binary;
+mod hidato;
+mod kakuro;
 mod kenken;
+mod kropki;
 mod magic_square;
+mod multi_sudoku;
+mod nonogram;
 mod simple;
+mod skyscrapers;
+mod star_battle;
 mod sudoku;
+
+// Unwraps the solution of a grid without inactive cells
+fn full_grid(solution: Vec<Vec<Option<u64>>>) -> Vec<Vec<u64>> {
+    solution
+        .into_iter()
+        .map(|column| column.into_iter().map(Option::unwrap).collect())
+        .collect()
+}
@@ -0,0 +1,65 @@
+use crate::error::GridCspError;
+use crate::model::*;
+use crate::sat::GridCspSolver;
+
+fn assert_valid_sudoku(solution: &[Vec<Option<u64>>], offset: Cell, size: usize) {
+    let root = size.isqrt();
+    let value = |x: usize, y: usize| solution[offset.x + x][offset.y + y].unwrap();
+    let all: Vec<u64> = (1..=size as u64).collect();
+    for i in 0..size {
+        let mut row: Vec<u64> = (0..size).map(|x| value(x, i)).collect();
+        let mut column: Vec<u64> = (0..size).map(|y| value(i, y)).collect();
+        let mut square: Vec<u64> = (0..size)
+            .map(|j| value(i % root * root + j % root, i / root * root + j / root))
+            .collect();
+        row.sort();
+        column.sort();
+        square.sort();
+        assert_eq!(row, all);
+        assert_eq!(column, all);
+        assert_eq!(square, all);
+    }
+}
+
+#[test]
+fn twodoku_4x4() -> Result<(), GridCspError> {
+    let mut problem = MultiSudokuProblem::new(4);
+    problem.add_grid(Cell::new(0, 0));
+    problem.add_grid(Cell::new(2, 2));
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![
+        Cell::new(0, 0),
+        Cell::new(3, 2),
+        Cell::new(4, 5),
+    ])));
+    problem.add_constraint(
+        Constraint::Equal(2).over(CellGroup::List(vec![Cell::new(2, 0), Cell::new(5, 4)])),
+    );
+    let generic = GenericProblem::try_from(problem)?;
+    assert_eq!(generic.grid, GridDimensions::new(6, 6, 4));
+    assert_eq!(generic.active_cells().count(), 28);
+    let mut csp = GridCspSolver::try_from(generic)?;
+    let solution = csp.solve()?;
+    assert_eq!(solution[5][0], None);
+    assert_eq!(solution[0][5], None);
+    assert_valid_sudoku(&solution, Cell::new(0, 0), 4);
+    assert_valid_sudoku(&solution, Cell::new(2, 2), 4);
+    Ok(())
+}
+
+#[test]
+fn samurai_9x9() -> Result<(), GridCspError> {
+    let mut problem = MultiSudokuProblem::samurai(9);
+    problem.add_constraint(Constraint::Equal(5).over(CellGroup::List(vec![Cell::new(10, 10)])));
+    let offsets = problem.offsets.clone();
+    let generic = GenericProblem::try_from(problem)?;
+    assert_eq!(generic.grid, GridDimensions::new(21, 21, 9));
+    assert_eq!(generic.active_cells().count(), 369);
+    let mut csp = GridCspSolver::try_from(generic)?;
+    let solution = csp.solve()?;
+    assert_eq!(solution[10][10], Some(5));
+    assert_eq!(solution[10][0], None);
+    for offset in offsets {
+        assert_valid_sudoku(&solution, offset, 9);
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use super::full_grid;
+use crate::error::GridCspError;
+use crate::model::*;
+use crate::sat::GridCspSolver;
+
+#[test]
+fn nonogram_10x10() -> Result<(), GridCspError> {
+    let problem: NonogramProblem = serde_json::from_str(
+        r#"{
+            "rows": [[4], [6], [2, 2, 2], [8], [1, 1], [1, 2, 1], [1, 1, 1, 1], [2], [4], [2, 2]],
+            "columns": [[2, 1], [3, 1], [2, 2, 1, 1], [4, 1, 2], [4, 1, 2], [2, 2, 3], [3, 1, 2], [2, 1, 1], [], [0]],
+            "constraints": []
+        }"#,
+    )
+    .unwrap();
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    let solution = full_grid(csp.solve_unique()?);
+    let picture = [
+        "..####....",
+        ".######...",
+        "##.##.##..",
+        "########..",
+        "..#..#....",
+        ".#.##.#...",
+        "#.#..#.#..",
+        "....##....",
+        "...####...",
+        "..##..##..",
+    ];
+    for (y, row) in picture.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            assert_eq!(solution[x][y], (c == '#') as u64);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn nonogram_masked_row() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(5, 1, 1).with_number_min(0));
+    problem.deactivate(Cell::new(2, 0));
+    problem.add_constraint(Constraint::Equal(0).over(CellGroup::List(vec![Cell::new(0, 0)])));
+    // Inactive cells are gaps, so a run cannot span one
+    let mut spanning = problem.clone();
+    spanning.add_constraint(Constraint::Runs(vec![3]).over(CellGroup::Row(0)));
+    let mut csp = GridCspSolver::try_from(spanning)?;
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+    problem.add_constraint(Constraint::Runs(vec![1, 2]).over(CellGroup::Row(0)));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let solution = csp.solve_unique()?;
+    assert_eq!(
+        solution,
+        vec![
+            vec![Some(0)],
+            vec![Some(1)],
+            vec![None],
+            vec![Some(1)],
+            vec![Some(1)]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn nonogram_invalid_runs() {
+    let mut problem = GenericProblem::new(GridDimensions::new(5, 5, 1).with_number_min(0));
+    problem.add_constraint(Constraint::Runs(vec![2, 3]).over(CellGroup::Row(0)));
+    assert_eq!(
+        problem.validate(),
+        Err(GridCspError::ConstrainedGroupTooSmall)
+    );
+    let mut problem = GenericProblem::new(GridDimensions::new(5, 5, 2));
+    problem.add_constraint(Constraint::Runs(vec![2]).over(CellGroup::Row(0)));
+    assert_eq!(problem.validate(), Err(GridCspError::UnsupportedConstraint));
+}
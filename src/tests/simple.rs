@@ -1,6 +1,7 @@
+use super::full_grid;
 use crate::error::GridCspError;
 use crate::model::*;
-use crate::sat::GridCspSolver;
+use crate::sat::{Encoding, GridCspSolver, Optimum, SolverOptions};
 
 #[test]
 fn simple_2x2() -> Result<(), GridCspError> {
@@ -33,7 +34,7 @@ fn simple_2x2() -> Result<(), GridCspError> {
     )
     .unwrap();
     let mut csp = GridCspSolver::try_from(problem)?;
-    let solution = csp.solve_unique()?;
+    let solution = full_grid(csp.solve_unique()?);
     assert_eq!(solution, vec![vec![2, 1], vec![1, 2]]);
     Ok(())
 }
@@ -50,11 +51,11 @@ fn simple_3x3() -> Result<(), GridCspError> {
         );
     }
     let problem = GenericProblem {
-        grid: GridDimensions::new(3, 3, 3),
         constraints: groups,
+        ..GenericProblem::new(GridDimensions::new(3, 3, 3))
     };
     let mut csp = GridCspSolver::try_from(problem)?;
-    let solution = csp.solve_unique()?;
+    let solution = full_grid(csp.solve_unique()?);
     assert_eq!(solution, vec![vec![1, 3, 2], vec![3, 2, 1], vec![2, 1, 3]]);
     Ok(())
 }
@@ -69,10 +70,502 @@ fn underconstrained_3x3() -> Result<(), GridCspError> {
         groups.push(CellGroup::List(vec![Cell::new(i, i)]).constrainted_by(Constraint::Equal(1)));
     }
     let problem = GenericProblem {
-        grid: GridDimensions::new(3, 3, 3),
         constraints: groups,
+        ..GenericProblem::new(GridDimensions::new(3, 3, 3))
     };
     let mut csp = GridCspSolver::try_from(problem)?;
     assert_eq!(csp.solve_unique(), Err(GridCspError::SolutionNotUnique));
     Ok(())
 }
+
+#[test]
+fn masked_4x4() -> Result<(), GridCspError> {
+    let size = 4;
+    let mut problem = GenericProblem::new(GridDimensions::new(size, size, 3));
+    problem.set_active_cells(
+        (0..size)
+            .flat_map(|x| (0..size).map(move |y| Cell::new(x, y)))
+            .filter(|c| c.x != c.y)
+            .collect::<Vec<Cell>>(),
+    );
+    for i in 0..size {
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Row(i)));
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Column(i)));
+    }
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![Cell::new(0, 1)])));
+    problem.add_constraint(Constraint::Equal(3).over(CellGroup::List(vec![Cell::new(1, 2)])));
+    problem.add_constraint(Constraint::Equal(2).over(CellGroup::List(vec![Cell::new(2, 0)])));
+    assert_eq!(CellGroup::Row(0).size(&problem), 3);
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let solution = csp.solve_unique()?;
+    assert_eq!(
+        solution,
+        vec![
+            vec![None, Some(1), Some(2), Some(3)],
+            vec![Some(1), None, Some(3), Some(2)],
+            vec![Some(2), Some(3), None, Some(1)],
+            vec![Some(3), Some(2), Some(1), None]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn unique_large_group() -> Result<(), GridCspError> {
+    // Too many cells to forbid equal pairs, so values are counted instead
+    for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+        let mut problem = GenericProblem::new(GridDimensions::new(12, 1, 12));
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Row(0)));
+        for x in 0..11 {
+            let cell = Cell::new(x, 0);
+            problem
+                .add_constraint(Constraint::Equal(12 - x as u64).over(CellGroup::List(vec![cell])));
+        }
+        let options = SolverOptions::default().with_encoding(encoding);
+        let mut csp = GridCspSolver::from_problem(problem, options)?;
+        assert_eq!(csp.solve_unique()?[11][0], Some(1));
+        assert_eq!(
+            csp.solve_with_assumptions(&[(Cell::new(11, 0), 2)]),
+            Err(GridCspError::NoSolution)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn masked_inactive_constraint() {
+    let mut problem = GenericProblem::new(GridDimensions::new(2, 2, 2));
+    problem.deactivate(Cell::new(1, 1));
+    problem.add_constraint(Constraint::Add(3).over(CellGroup::Row(0)));
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![Cell::new(1, 1)])));
+    assert!(matches!(
+        problem.validate(),
+        Err(GridCspError::InactiveCell(1, 1))
+    ));
+}
+
+#[test]
+fn count_twice_6x3() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(6, 3, 3));
+    for y in 0..3 {
+        for value in 1..=3 {
+            problem.add_constraint(Constraint::exactly(value, 2).over(CellGroup::Row(y)));
+        }
+        problem.add_constraint(Constraint::MaxRun(1).over(CellGroup::Row(y)));
+    }
+    for x in 0..6 {
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Column(x)));
+    }
+    problem.add_constraint(Constraint::at_least(3, 2).over(CellGroup::Column(0)));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+
+    let mut problem = GenericProblem::new(GridDimensions::new(6, 3, 3));
+    for y in 0..3 {
+        for value in 1..=3 {
+            problem.add_constraint(Constraint::exactly(value, 2).over(CellGroup::Row(y)));
+        }
+        problem.add_constraint(Constraint::MaxRun(1).over(CellGroup::Row(y)));
+    }
+    problem.add_constraint(Constraint::at_most(1, 0).over(CellGroup::Column(0)));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let solution = full_grid(csp.solve()?);
+    for y in 0..3 {
+        let row: Vec<u64> = solution.iter().map(|column| column[y]).collect();
+        for value in 1..=3 {
+            assert_eq!(row.iter().filter(|v| **v == value).count(), 2);
+        }
+        assert!(row.windows(2).all(|w| w[0] != w[1]));
+        assert_ne!(row[0], 1);
+    }
+    Ok(())
+}
+
+#[test]
+fn count_invalid_bounds() {
+    let mut problem = GenericProblem::new(GridDimensions::new(3, 3, 3));
+    problem.add_constraint(
+        Constraint::Count {
+            value: 1,
+            min: 2,
+            max: 1,
+        }
+        .over(CellGroup::Row(0)),
+    );
+    assert_eq!(
+        problem.validate(),
+        Err(GridCspError::InvalidCountBounds(2, 1))
+    );
+    let mut problem = GenericProblem::new(GridDimensions::new(3, 3, 3));
+    problem.add_constraint(Constraint::at_least(1, 4).over(CellGroup::Row(0)));
+    assert_eq!(
+        problem.validate(),
+        Err(GridCspError::ConstrainedGroupTooSmall)
+    );
+}
+
+#[test]
+fn connected_4x4() -> Result<(), GridCspError> {
+    let blank_pair = |a: Cell, b: Cell, connected: bool| {
+        let mut problem = GenericProblem::new(GridDimensions::new(4, 4, 1).with_number_min(0));
+        for i in 0..4 {
+            problem.add_constraint(Constraint::exactly(0, 1).over(CellGroup::Row(i)));
+            problem.add_constraint(Constraint::exactly(0, 1).over(CellGroup::Column(i)));
+        }
+        problem.add_constraint(Constraint::Equal(0).over(CellGroup::List(vec![a, b])));
+        if connected {
+            problem.add_rule(GlobalRule::Connected(1));
+        }
+        problem
+    };
+
+    let mut csp = GridCspSolver::try_from(blank_pair(Cell::new(0, 0), Cell::new(1, 1), false))?;
+    assert!(csp.solve().is_ok());
+    let mut csp = GridCspSolver::try_from(blank_pair(Cell::new(0, 0), Cell::new(1, 1), true))?;
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+
+    let mut csp = GridCspSolver::try_from(blank_pair(Cell::new(0, 0), Cell::new(2, 3), true))?;
+    let solution = full_grid(csp.solve_unique()?);
+    assert_eq!(
+        solution,
+        vec![
+            vec![0, 1, 1, 1],
+            vec![1, 1, 0, 1],
+            vec![1, 1, 1, 0],
+            vec![1, 0, 1, 1]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn connected_masked() -> Result<(), GridCspError> {
+    let mut problem: GenericProblem = serde_json::from_str(
+        r#"{
+            "grid": {"width": 3, "height": 3, "number_min": 0, "number_max": 1},
+            "constraints": [
+                {
+                    "constraint": { "Count": { "value": 1, "min": 3, "max": 3 } },
+                    "group": { "Square": { "x": 0, "y": 0, "width": 3, "height": 3 } }
+                },
+                {
+                    "constraint": { "Equal": 1 },
+                    "group": { "List": [{ "x": 0, "y": 0 }, { "x": 2, "y": 0 }] }
+                }
+            ],
+            "rules": [{ "Connected": 1 }]
+        }"#,
+    )
+    .unwrap();
+    let mut csp = GridCspSolver::try_from(problem.clone())?;
+    assert_eq!(csp.solve_unique()?[1][0], Some(1));
+
+    problem.deactivate(Cell::new(1, 0));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+    Ok(())
+}
+
+#[test]
+fn assumptions_4x4() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(4, 4, 4));
+    for i in 0..4 {
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Row(i)));
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Column(i)));
+    }
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![Cell::new(0, 0)])));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let candidates = |csp: &mut GridCspSolver, cell: Cell| {
+        (1..=4)
+            .filter(|v| csp.solve_with_assumptions(&[(cell, *v)]).is_ok())
+            .collect::<Vec<u64>>()
+    };
+    assert_eq!(candidates(&mut csp, Cell::new(1, 0)), vec![2, 3, 4]);
+    assert_eq!(
+        csp.solve_with_assumptions(&[(Cell::new(1, 0), 2), (Cell::new(2, 0), 2)]),
+        Err(GridCspError::NoSolution)
+    );
+    let solution =
+        full_grid(csp.solve_with_assumptions(&[(Cell::new(1, 0), 2), (Cell::new(0, 1), 3)])?);
+    assert_eq!((solution[1][0], solution[0][1]), (2, 3));
+
+    // Clauses added between searches are kept, assumptions are not
+    let var = csp.get_value_var(Cell::new(1, 0), 2)?;
+    csp.add_clause(vec![-var]);
+    assert_eq!(candidates(&mut csp, Cell::new(1, 0)), vec![3, 4]);
+    assert_eq!(candidates(&mut csp, Cell::new(0, 1)), vec![2, 3, 4]);
+    assert_ne!(csp.solve()?[1][0], Some(2));
+
+    assert_eq!(
+        csp.solve_with_assumptions(&[(Cell::new(4, 0), 1)]),
+        Err(GridCspError::CellOutOfBound(4, 0))
+    );
+    assert_eq!(
+        csp.solve_with_assumptions(&[(Cell::new(0, 0), 5)]),
+        Err(GridCspError::ValueOutOfBound(5))
+    );
+    Ok(())
+}
+
+#[test]
+fn optimize_latin_square() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(4, 4, 4));
+    for i in 0..4 {
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Row(i)));
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Column(i)));
+    }
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![Cell::new(0, 0)])));
+    let mut csp = GridCspSolver::try_from(problem)?;
+
+    let diagonal = CellGroup::List((1..4).map(|i| Cell::new(i, i)).collect());
+    let optimum = csp.optimize(&Objective::Maximize(vec![ObjectiveTerm::Values {
+        group: diagonal.clone(),
+        weight: 1,
+    }]))?;
+    // The four of the first row keeps one of the diagonal cells from being a four
+    assert_eq!(optimum.value, 11);
+    assert_eq!(
+        (1..4).map(|i| optimum.solution[i][i].unwrap()).sum::<u64>(),
+        11
+    );
+
+    let Optimum { solution, value } = csp.optimize(&Objective::Minimize(vec![
+        ObjectiveTerm::Count {
+            group: diagonal,
+            value: 4,
+            weight: -1,
+        },
+        ObjectiveTerm::Count {
+            group: CellGroup::Square {
+                x: 0,
+                y: 0,
+                height: 4,
+                width: 4,
+            },
+            value: 4,
+            weight: 1,
+        },
+    ]))?;
+    assert_eq!(value, 2);
+    let solution = full_grid(solution);
+    assert_eq!((1..4).filter(|i| solution[*i][*i] == 4).count(), 2);
+
+    let optimum = csp.optimize(&Objective::Minimize(vec![ObjectiveTerm::Values {
+        group: CellGroup::Row(0),
+        weight: 2,
+    }]))?;
+    assert_eq!(optimum.value, 20);
+
+    // Bounds from previous searches do not remain
+    assert!(
+        csp.solve_unique()
+            .is_err_and(|e| e == GridCspError::SolutionNotUnique)
+    );
+    assert_eq!(
+        csp.optimize(&Objective::Maximize(vec![ObjectiveTerm::Count {
+            group: CellGroup::Row(0),
+            value: 5,
+            weight: 1,
+        }])),
+        Err(GridCspError::ValueOutOfBound(5))
+    );
+    Ok(())
+}
+
+#[test]
+fn optimize_covering() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(4, 3, 1).with_number_min(0));
+    for x in 0..4 {
+        problem.add_constraint(
+            Constraint::Count {
+                value: 1,
+                min: 1,
+                max: 3,
+            }
+            .over(CellGroup::Column(x)),
+        );
+    }
+    for y in 0..3 {
+        problem.add_constraint(
+            Constraint::Count {
+                value: 1,
+                min: 1,
+                max: 4,
+            }
+            .over(CellGroup::Row(y)),
+        );
+    }
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let all = CellGroup::Square {
+        x: 0,
+        y: 0,
+        height: 3,
+        width: 4,
+    };
+    let optimum = csp.optimize(&Objective::Minimize(vec![ObjectiveTerm::Values {
+        group: all.clone(),
+        weight: 1,
+    }]))?;
+    assert_eq!(optimum.value, 4);
+    let optimum = csp.optimize(&Objective::Maximize(vec![ObjectiveTerm::Count {
+        group: all,
+        value: 1,
+        weight: -3,
+    }]))?;
+    assert_eq!(optimum.value, -12);
+    Ok(())
+}
+
+#[test]
+fn optimize_large_weights() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(3, 1, 3));
+    problem.add_constraint(Constraint::Unique.over(CellGroup::Row(0)));
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![Cell::new(1, 0)])));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let first = CellGroup::List(vec![Cell::new(0, 0)]);
+    let second = CellGroup::List(vec![Cell::new(1, 0)]);
+
+    // Costs sharing a large factor are scaled down before being encoded
+    let optimum = csp.optimize(&Objective::Minimize(vec![ObjectiveTerm::Values {
+        group: first.clone(),
+        weight: 1 << 40,
+    }]))?;
+    assert_eq!(optimum.value, 2 << 40);
+    assert_eq!(optimum.solution[0][0], Some(2));
+
+    // Every solution has too high a cost to be bounded in unary, which takes a few bits in binary
+    let optimum = csp.optimize(&Objective::Maximize(vec![
+        ObjectiveTerm::Values {
+            group: first.clone(),
+            weight: 1,
+        },
+        ObjectiveTerm::Values {
+            group: second,
+            weight: 5000,
+        },
+    ]))?;
+    assert_eq!(optimum.value, 5003);
+    assert_eq!(
+        csp.optimize(&Objective::Minimize(vec![ObjectiveTerm::Values {
+            group: first,
+            weight: i64::MAX,
+        }])),
+        Err(GridCspError::CostOverflow)
+    );
+    assert!(csp.solve().is_ok());
+    Ok(())
+}
+
+#[test]
+fn optimize_roster() -> Result<(), GridCspError> {
+    let mut csp = GridCspSolver::new(GridDimensions::new(10, 1, 200))?;
+    let optimum = csp.optimize(&Objective::Maximize(vec![ObjectiveTerm::Values {
+        group: CellGroup::Row(0),
+        weight: 1,
+    }]))?;
+    assert_eq!(optimum.value, 2000);
+    assert!(optimum.solution.iter().all(|column| column[0] == Some(200)));
+    Ok(())
+}
+
+#[test]
+fn soft_constraints_3x3() -> Result<(), GridCspError> {
+    let mut problem: GenericProblem = serde_json::from_str(
+        r#"{
+            "grid": {"width": 3, "height": 3, "number_max": 3},
+            "constraints": [
+                {
+                    "constraint": { "Equal": 1 },
+                    "group": { "List": [{ "x": 0, "y": 0 }] },
+                    "weight": 2
+                },
+                {
+                    "constraint": { "Equal": 1 },
+                    "group": { "List": [{ "x": 1, "y": 0 }] },
+                    "weight": 3
+                },
+                {
+                    "constraint": { "Equal": 3 },
+                    "group": { "List": [{ "x": 1, "y": 1 }] },
+                    "weight": 1
+                },
+                {
+                    "constraint": { "Add": 9 },
+                    "group": { "Row": 2 },
+                    "weight": 5
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    for i in 0..3 {
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Row(i)));
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Column(i)));
+    }
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let soft = csp.solve_soft()?;
+    assert_eq!(soft.violated, vec![0, 3]);
+    assert_eq!(soft.violated_weight, 7);
+    let solution = full_grid(soft.solution);
+    assert_eq!((solution[1][0], solution[1][1]), (1, 3));
+
+    // Soft constraints added in a scope go away with it
+    csp.push()?;
+    csp.add_constrained_group(
+        Constraint::Equal(2)
+            .over(CellGroup::List(vec![Cell::new(1, 0)]))
+            .with_weight(4),
+    )?;
+    let soft = csp.solve_soft()?;
+    assert_eq!(soft.violated, vec![1, 3]);
+    assert_eq!(soft.violated_weight, 8);
+    csp.pop()?;
+    assert_eq!(csp.solve_soft()?.violated, vec![0, 3]);
+
+    // Two of them are always violated, whose weights add up beyond 64 bits
+    csp.push()?;
+    for value in 1..=3 {
+        csp.add_constrained_group(
+            Constraint::Equal(value)
+                .over(CellGroup::List(vec![Cell::new(2, 2)]))
+                .with_weight(u64::MAX),
+        )?;
+    }
+    assert_eq!(csp.solve_soft(), Err(GridCspError::CostOverflow));
+    csp.pop()?;
+    assert_eq!(csp.solve_soft()?.violated_weight, 7);
+
+    assert_eq!(
+        csp.add_constrained_group(
+            Constraint::Equal(2)
+                .over(CellGroup::List(vec![Cell::new(1, 0)]))
+                .with_weight(0),
+        ),
+        Err(GridCspError::InvalidWeight)
+    );
+    Ok(())
+}
+
+#[test]
+fn soft_constraints_heavy_weights() -> Result<(), GridCspError> {
+    let mut problem = GenericProblem::new(GridDimensions::new(3, 3, 3));
+    for i in 0..3 {
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Row(i)));
+        problem.add_constraint(Constraint::Unique.over(CellGroup::Column(i)));
+    }
+    let soft = |value: u64, x: usize, y: usize, weight: u64| {
+        Constraint::Equal(value)
+            .over(CellGroup::List(vec![Cell::new(x, y)]))
+            .with_weight(weight)
+    };
+    problem.add_constraint(soft(1, 0, 0, 1_000_003));
+    problem.add_constraint(soft(2, 1, 0, 1_000_000_007));
+    problem.add_constraint(soft(3, 1, 1, 999_999_937));
+    problem.add_constraint(soft(2, 0, 0, 5));
+    let mut csp = GridCspSolver::try_from(problem)?;
+    // However heavy the soft constraints the first model violates, only the light one is
+    let soft = csp.solve_soft()?;
+    assert_eq!(soft.violated, vec![9]);
+    assert_eq!(soft.violated_weight, 5);
+    Ok(())
+}
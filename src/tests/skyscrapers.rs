@@ -0,0 +1,92 @@
+use super::full_grid;
+use crate::error::GridCspError;
+use crate::model::*;
+use crate::sat::{Encoding, GridCspSolver, SolverOptions};
+
+#[test]
+fn skyscrapers_4x4() -> Result<(), GridCspError> {
+    let problem: SkyscrapersProblem = serde_json::from_str(
+        r#"{
+            "grid_size": 4,
+            "constraints": [
+                {
+                    "constraint": { "Visible": 4 },
+                    "group": { "Row": 0 }
+                },
+                {
+                    "constraint": { "Visible": 2 },
+                    "group": { "ReversedRow": 1 }
+                },
+                {
+                    "constraint": { "Visible": 1 },
+                    "group": { "ReversedColumn": 1 }
+                },
+                {
+                    "constraint": { "Visible": 2 },
+                    "group": { "ReversedColumn": 2 }
+                },
+                {
+                    "constraint": { "Visible": 4 },
+                    "group": { "ReversedColumn": 3 }
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    let solution = full_grid(csp.solve_unique()?);
+    assert_eq!(
+        solution,
+        vec![
+            vec![1, 2, 4, 3],
+            vec![2, 1, 3, 4],
+            vec![3, 4, 1, 2],
+            vec![4, 3, 2, 1]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn skyscrapers_impossible_clue() {
+    for visible in [0, 5] {
+        let mut problem = SkyscrapersProblem::new(4);
+        problem.add_constraint(Constraint::Visible(visible).over(CellGroup::Row(0)));
+        assert_eq!(
+            GridCspSolver::try_from(GenericProblem::from(problem)).err(),
+            Some(GridCspError::InvalidVisibleCount(visible))
+        );
+    }
+}
+
+#[test]
+fn skyscrapers_5x5() -> Result<(), GridCspError> {
+    let mut problem = SkyscrapersProblem::new(5);
+    let rows = [(3, 1), (3, 2), (2, 4), (1, 3), (2, 3)];
+    let columns = [(3, 2), (2, 3), (3, 1), (2, 3), (1, 4)];
+    for (i, (forward, backward)) in rows.into_iter().enumerate() {
+        problem.add_constraint(Constraint::Visible(forward).over(CellGroup::Row(i)));
+        problem.add_constraint(Constraint::Visible(backward).over(CellGroup::ReversedRow(i)));
+    }
+    for (i, (forward, backward)) in columns.into_iter().enumerate() {
+        problem.add_constraint(Constraint::Visible(forward).over(CellGroup::Column(i)));
+        problem.add_constraint(Constraint::Visible(backward).over(CellGroup::ReversedColumn(i)));
+    }
+    let problem = GenericProblem::from(problem);
+    for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+        let options = SolverOptions::default().with_encoding(encoding);
+        let mut csp = GridCspSolver::from_problem(problem.clone(), options)?;
+        let solution = full_grid(csp.solve_unique()?);
+        assert_eq!(
+            solution,
+            vec![
+                vec![2, 1, 3, 5, 4],
+                vec![4, 3, 5, 2, 1],
+                vec![3, 2, 4, 1, 5],
+                vec![1, 5, 2, 4, 3],
+                vec![5, 4, 1, 3, 2]
+            ]
+        );
+    }
+    Ok(())
+}
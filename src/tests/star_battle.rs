@@ -0,0 +1,47 @@
+use super::full_grid;
+use crate::error::GridCspError;
+use crate::model::*;
+use crate::sat::GridCspSolver;
+
+#[test]
+fn star_battle_6x6() -> Result<(), GridCspError> {
+    let layout = ["222244", "022222", "033333", "013333", "013333", "013335"];
+    let mut problem = StarBattleProblem::new(6, 1);
+    for region in '0'..='5' {
+        problem.add_region(CellGroup::List(
+            layout
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .filter(move |(_, c)| *c == region)
+                        .map(move |(x, _)| Cell::new(x, y))
+                })
+                .collect(),
+        ));
+    }
+    let mut csp = GridCspSolver::try_from(GenericProblem::from(problem))?;
+    let solution = full_grid(csp.solve_unique()?);
+    let stars = [2, 4, 1, 3, 0, 5];
+    for (x, column) in solution.iter().enumerate() {
+        for (y, value) in column.iter().enumerate() {
+            assert_eq!(*value, (stars[x] == y) as u64);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn star_battle_touching() {
+    let mut problem = GenericProblem::new(GridDimensions::new(2, 2, 1).with_number_min(0));
+    problem.add_rule(GlobalRule::NoTouching(1));
+    problem.add_constraint(Constraint::exactly(1, 1).over(CellGroup::Row(0)));
+    problem.add_constraint(Constraint::exactly(1, 1).over(CellGroup::Row(1)));
+    let mut csp = GridCspSolver::try_from(problem).unwrap();
+    assert_eq!(csp.solve(), Err(GridCspError::NoSolution));
+
+    let mut problem = GenericProblem::new(GridDimensions::new(2, 2, 1).with_number_min(0));
+    problem.add_rule(GlobalRule::NoTouching(2));
+    assert_eq!(problem.validate(), Err(GridCspError::ValueOutOfBound(2)));
+}
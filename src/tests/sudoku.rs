@@ -1,9 +1,9 @@
+use super::full_grid;
 use crate::error::GridCspError;
 use crate::model::*;
-use crate::sat::GridCspSolver;
+use crate::sat::{Encoding, GridCspSolver, SolverOptions};
 
-#[test]
-fn sudoku_9x9() -> Result<(), GridCspError> {
+fn sudoku_problem() -> Result<GenericProblem, GridCspError> {
     let problem: SudokuProblem = serde_json::from_str(
         r#"{
             "grid_size": 9,
@@ -44,8 +44,13 @@ fn sudoku_9x9() -> Result<(), GridCspError> {
         }"#,
     )
     .unwrap();
-    let mut csp = GridCspSolver::try_from(GenericProblem::try_from(problem)?)?;
-    let solution = csp.solve_unique()?;
+    GenericProblem::try_from(problem)
+}
+
+#[test]
+fn sudoku_9x9() -> Result<(), GridCspError> {
+    let mut csp = GridCspSolver::try_from(sudoku_problem()?)?;
+    let solution = full_grid(csp.solve_unique()?);
     assert_eq!(
         solution,
         vec![
@@ -62,3 +67,282 @@ fn sudoku_9x9() -> Result<(), GridCspError> {
     );
     Ok(())
 }
+
+#[test]
+fn sudoku_candidates() -> Result<(), GridCspError> {
+    let mut csp = GridCspSolver::try_from(sudoku_problem()?)?;
+    let solution = full_grid(csp.solve_unique()?);
+    // Each query starts from the solver built so far and leaves it as it was
+    for (x, column) in solution.iter().enumerate() {
+        let cell = Cell::new(x, 4);
+        let candidates: Vec<u64> = (1..=9)
+            .filter(|v| csp.solve_with_assumptions(&[(cell, *v)]).is_ok())
+            .collect();
+        assert_eq!(candidates, vec![column[4]]);
+    }
+    assert_eq!(full_grid(csp.solve_unique()?), solution);
+    Ok(())
+}
+
+#[test]
+fn sandwich_sudoku_9x9() -> Result<(), GridCspError> {
+    let mut problem = SudokuProblem::new(9);
+    let givens: [(u64, &[(usize, usize)]); 8] = [
+        (1, &[(5, 0), (6, 4)]),
+        (2, &[(1, 5), (3, 8)]),
+        (3, &[(2, 7), (4, 5), (8, 1)]),
+        (4, &[(3, 7), (7, 1)]),
+        (5, &[(0, 2), (8, 6)]),
+        (6, &[(0, 6), (6, 8)]),
+        (7, &[(4, 3), (7, 6)]),
+        (8, &[(3, 0)]),
+    ];
+    for (value, cells) in givens {
+        let cells = cells.iter().map(|(x, y)| Cell::new(*x, *y)).collect();
+        problem.add_constraint(Constraint::Equal(value).over(CellGroup::List(cells)));
+    }
+    for (i, sum) in [11, 21, 22, 30, 15, 3, 0, 2, 7].into_iter().enumerate() {
+        problem.add_constraint(
+            Constraint::Sandwich {
+                low: 1,
+                high: 9,
+                sum,
+            }
+            .over(CellGroup::Row(i)),
+        );
+    }
+    let problem = GenericProblem::try_from(problem)?;
+    for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+        let options = SolverOptions::default().with_encoding(encoding);
+        let mut csp = GridCspSolver::from_problem(problem.clone(), options)?;
+        let solution = full_grid(csp.solve_unique()?);
+        assert_eq!(
+            solution,
+            vec![
+                vec![2, 1, 5, 3, 4, 7, 6, 8, 9],
+                vec![3, 8, 9, 1, 6, 2, 4, 5, 7],
+                vec![7, 6, 4, 5, 9, 8, 2, 3, 1],
+                vec![8, 7, 3, 6, 5, 1, 9, 4, 2],
+                vec![4, 9, 2, 7, 8, 3, 1, 6, 5],
+                vec![1, 5, 6, 4, 2, 9, 8, 7, 3],
+                vec![5, 2, 7, 8, 1, 4, 3, 9, 6],
+                vec![6, 4, 1, 9, 3, 5, 7, 2, 8],
+                vec![9, 3, 8, 2, 7, 6, 5, 1, 4]
+            ]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn arrow_sudoku_9x9() -> Result<(), GridCspError> {
+    let problem: SudokuProblem = serde_json::from_str(
+        r#"{
+            "grid_size": 9,
+            "constraints": [
+                {
+                    "constraint": { "Equal": 1 },
+                    "group": { "List": [{ "x": 5, "y": 0 }, { "x": 6, "y": 4 }] }
+                },
+                {
+                    "constraint": { "Equal": 2 },
+                    "group": { "List": [{ "x": 1, "y": 5 }, { "x": 3, "y": 8 }] }
+                },
+                {
+                    "constraint": { "Equal": 3 },
+                    "group": { "List": [{ "x": 2, "y": 7 }, { "x": 4, "y": 5 }, { "x": 8, "y": 1 }] }
+                },
+                {
+                    "constraint": { "Equal": 4 },
+                    "group": { "List": [{ "x": 3, "y": 7 }, { "x": 7, "y": 1 }] }
+                },
+                {
+                    "constraint": { "Equal": 5 },
+                    "group": { "List": [{ "x": 0, "y": 2 }, { "x": 8, "y": 6 }] }
+                },
+                {
+                    "constraint": { "Equal": 6 },
+                    "group": { "List": [{ "x": 0, "y": 6 }, { "x": 6, "y": 8 }] }
+                },
+                {
+                    "constraint": { "Equal": 7 },
+                    "group": { "List": [{ "x": 4, "y": 3 }, { "x": 7, "y": 6 }] }
+                },
+                {
+                    "constraint": { "Equal": 8 },
+                    "group": { "List": [{ "x": 3, "y": 0 }] }
+                },
+                {
+                    "constraint": { "ArrowSum": { "circle": { "x": 6, "y": 3 } } },
+                    "group": { "List": [{ "x": 6, "y": 4 }, { "x": 6, "y": 5 }, { "x": 6, "y": 6 }] }
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let problem = GenericProblem::try_from(problem)?;
+    for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+        let options = SolverOptions::default().with_encoding(encoding);
+        let mut csp = GridCspSolver::from_problem(problem.clone(), options)?;
+        let solution = full_grid(csp.solve_unique()?);
+        assert_eq!(solution[6], vec![5, 2, 7, 8, 1, 4, 3, 9, 6]);
+    }
+    Ok(())
+}
+
+#[test]
+fn miracle_sudoku_9x9() -> Result<(), GridCspError> {
+    let mut problem = SudokuProblem::new(9);
+    problem.add_constraint(Constraint::Equal(1).over(CellGroup::List(vec![Cell::new(2, 4)])));
+    problem.add_constraint(Constraint::Equal(2).over(CellGroup::List(vec![Cell::new(6, 5)])));
+    let mut problem = GenericProblem::try_from(problem)?;
+    problem.add_rule(GlobalRule::AntiKing);
+    problem.add_rule(GlobalRule::AntiKnight);
+    problem.add_rule(GlobalRule::NonConsecutiveOrthogonal);
+    let mut csp = GridCspSolver::try_from(problem)?;
+    let solution = full_grid(csp.solve_unique()?);
+    assert_eq!(
+        solution,
+        vec![
+            vec![4, 7, 1, 8, 2, 5, 3, 6, 9],
+            vec![8, 2, 5, 3, 6, 9, 7, 1, 4],
+            vec![3, 6, 9, 7, 1, 4, 2, 5, 8],
+            vec![7, 1, 4, 2, 5, 8, 6, 9, 3],
+            vec![2, 5, 8, 6, 9, 3, 1, 4, 7],
+            vec![6, 9, 3, 1, 4, 7, 5, 8, 2],
+            vec![1, 4, 7, 5, 8, 2, 9, 3, 6],
+            vec![5, 8, 2, 9, 3, 6, 4, 7, 1],
+            vec![9, 3, 6, 4, 7, 1, 8, 2, 5]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn line_sudoku_9x9() -> Result<(), GridCspError> {
+    let mut problem = SudokuProblem::new(9);
+    let givens = [
+        (1, vec![Cell::new(4, 6), Cell::new(8, 5)]),
+        (2, vec![Cell::new(0, 4), Cell::new(2, 6), Cell::new(8, 7)]),
+        (3, vec![Cell::new(5, 2), Cell::new(8, 1)]),
+        (
+            4,
+            vec![
+                Cell::new(0, 0),
+                Cell::new(3, 2),
+                Cell::new(5, 4),
+                Cell::new(7, 6),
+                Cell::new(8, 3),
+            ],
+        ),
+        (5, vec![Cell::new(7, 0)]),
+        (
+            6,
+            vec![
+                Cell::new(1, 4),
+                Cell::new(2, 1),
+                Cell::new(3, 6),
+                Cell::new(8, 2),
+            ],
+        ),
+        (7, vec![Cell::new(0, 1), Cell::new(5, 5), Cell::new(7, 7)]),
+        (
+            9,
+            vec![
+                Cell::new(0, 8),
+                Cell::new(6, 6),
+                Cell::new(7, 3),
+                Cell::new(8, 0),
+            ],
+        ),
+    ];
+    for (value, cells) in givens {
+        problem.add_constraint(Constraint::Equal(value).over(CellGroup::List(cells)));
+    }
+    let mut csp = GridCspSolver::try_from(GenericProblem::try_from(problem.clone())?)?;
+    assert_eq!(csp.solve_unique(), Err(GridCspError::SolutionNotUnique));
+
+    let lines = [
+        (
+            LineConstraint::Renban,
+            vec![
+                Cell::new(3, 2),
+                Cell::new(2, 1),
+                Cell::new(1, 2),
+                Cell::new(0, 1),
+            ],
+        ),
+        (
+            LineConstraint::Palindrome,
+            vec![
+                Cell::new(5, 7),
+                Cell::new(4, 7),
+                Cell::new(3, 6),
+                Cell::new(2, 5),
+                Cell::new(3, 5),
+            ],
+        ),
+        (
+            LineConstraint::Between,
+            vec![
+                Cell::new(4, 2),
+                Cell::new(4, 3),
+                Cell::new(3, 3),
+                Cell::new(2, 4),
+            ],
+        ),
+        (
+            LineConstraint::Whisper(5),
+            vec![
+                Cell::new(4, 4),
+                Cell::new(5, 3),
+                Cell::new(6, 4),
+                Cell::new(6, 5),
+                Cell::new(6, 6),
+            ],
+        ),
+    ];
+    for (line, cells) in lines {
+        problem.add_constraint(Constraint::Line(line).over(CellGroup::List(cells)));
+    }
+    let problem = GenericProblem::try_from(problem)?;
+    for encoding in [Encoding::OneHot, Encoding::Order, Encoding::Log] {
+        let options = SolverOptions::default().with_encoding(encoding);
+        let mut csp = GridCspSolver::from_problem(problem.clone(), options)?;
+        let solution = full_grid(csp.solve_unique()?);
+        assert_eq!(
+            solution,
+            vec![
+                vec![4, 7, 1, 8, 2, 5, 3, 6, 9],
+                vec![8, 2, 5, 3, 6, 9, 7, 1, 4],
+                vec![3, 6, 9, 7, 1, 4, 2, 5, 8],
+                vec![7, 1, 4, 2, 5, 8, 6, 9, 3],
+                vec![2, 5, 8, 6, 9, 3, 1, 4, 7],
+                vec![6, 9, 3, 1, 4, 7, 5, 8, 2],
+                vec![1, 4, 7, 5, 8, 2, 9, 3, 6],
+                vec![5, 8, 2, 9, 3, 6, 4, 7, 1],
+                vec![9, 3, 6, 4, 7, 1, 8, 2, 5]
+            ]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn line_too_short() {
+    let mut problem = GenericProblem::new(GridDimensions::new(4, 4, 4));
+    problem.add_constraint(
+        Constraint::Line(LineConstraint::Between)
+            .over(CellGroup::List(vec![Cell::new(0, 0), Cell::new(1, 1)])),
+    );
+    assert_eq!(
+        problem.validate(),
+        Err(GridCspError::ConstrainedGroupTooSmall)
+    );
+    let mut problem = GenericProblem::new(GridDimensions::new(5, 5, 4));
+    problem.add_constraint(Constraint::Line(LineConstraint::Renban).over(CellGroup::Row(0)));
+    assert_eq!(
+        problem.validate(),
+        Err(GridCspError::ConstrainedGroupTooBig)
+    );
+}